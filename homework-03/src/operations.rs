@@ -3,6 +3,8 @@ pub mod operations {
     use std::error::Error;
     use std::str::FromStr;
 
+    // homework-03 keeps its own list, the operations of homework-04 are in
+    // homework_04::Registry
     pub const AVAILABLE_OPERATIONS: [&str; 7] = [
         "lowercase",
         "uppercase",
@@ -0,0 +1,530 @@
+// Batch mode: the pipeline is applied to every line of the input files
+//
+// homework-04 slugify --in titles.txt --out slugs.txt
+// homework-04 lowercase --in notes.txt --in-place --backup-suffix .orig
+//
+// Line endings are kept as they are. Lines which are not valid UTF-8 or on
+// which the pipeline fails are written unchanged and reported on stderr,
+// `file:line: error`. Lines dropped by a filter, like `matches`, are not
+// written at all.
+//
+// With --unique a result which was already written gets a number, so
+// `slugify --unique` gives `hello`, `hello-2`, `hello-3` for repeated titles.
+//
+// Pipelines with a stage like `sort-lines` read the whole file first and
+// every file is processed on its own. Such stages join the line endings,
+// the output uses "\r\n" when the input had it. When such a stage fails,
+// or a line is not valid UTF-8, the run stops and a file rewritten in place
+// is left as it was.
+use homework_04::slugs::Uniquifier;
+use homework_04::{Error, Options, Pipeline};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Number of processed lines and of the lines which failed
+#[derive(Debug, Default, PartialEq)]
+pub struct BatchReport {
+    pub lines: usize,
+    pub failed: usize,
+}
+
+impl BatchReport {
+    fn add(&mut self, other: BatchReport) {
+        self.lines += other.lines;
+        self.failed += other.failed;
+    }
+}
+
+// Splits the line ending off, "\r\n" and "\n" are recognized
+fn split_line_ending(line: &[u8]) -> (&[u8], &[u8]) {
+    if line.ends_with(b"\r\n") {
+        line.split_at(line.len() - 2)
+    } else if line.ends_with(b"\n") {
+        line.split_at(line.len() - 1)
+    } else {
+        (line, &[])
+    }
+}
+
+// The separator of the numbers is the one of the slugs
+fn uniquifier(pipeline: &Pipeline, options: &Options) -> Option<Uniquifier> {
+    let options = match pipeline.stages().last() {
+        Some(stage) => options.merged(&stage.options),
+        None => options.clone(),
+    };
+    if !options.flag("unique") {
+        return None;
+    }
+    Some(Uniquifier::new(options.get("separator").unwrap_or("-")))
+}
+
+// The failed lines are reported into `errors`
+pub fn process_lines<R: BufRead, W: Write>(
+    name: &str,
+    mut reader: R,
+    writer: &mut W,
+    errors: &mut dyn Write,
+    pipeline: &Pipeline,
+    options: &Options,
+    mut uniquifier: Option<&mut Uniquifier>,
+) -> Result<BatchReport, Error> {
+    if pipeline.is_whole_input() {
+        return process_all_lines(name, reader, writer, errors, pipeline, options, uniquifier);
+    }
+    let mut report = BatchReport::default();
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        report.lines += 1;
+        let (content, ending) = split_line_ending(&line);
+
+        let output = match std::str::from_utf8(content) {
+            Ok(content) => pipeline
+                .filter_with(content, options)
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(format!("invalid UTF-8: {}", e)),
+        };
+        let output = match (output, uniquifier.as_deref_mut()) {
+            (Ok(Some(output)), Some(uniquifier)) => Ok(Some(uniquifier.unique(&output))),
+            (output, _) => output,
+        };
+        match output {
+            Ok(Some(output)) => writer.write_all(output.as_bytes())?,
+            Ok(None) => continue,
+            Err(e) => {
+                writeln!(
+                    errors,
+                    "{}:{}: {}, line left unchanged",
+                    name, report.lines, e
+                )?;
+                report.failed += 1;
+                writer.write_all(content)?;
+            }
+        }
+        writer.write_all(ending)?;
+    }
+    Ok(report)
+}
+
+// Reads all the lines before running the pipeline on them
+fn process_all_lines<R: BufRead, W: Write>(
+    name: &str,
+    mut reader: R,
+    writer: &mut W,
+    errors: &mut dyn Write,
+    pipeline: &Pipeline,
+    options: &Options,
+    mut uniquifier: Option<&mut Uniquifier>,
+) -> Result<BatchReport, Error> {
+    let mut report = BatchReport::default();
+    let mut lines = Vec::new();
+    let mut ending: &[u8] = b"\n";
+    let mut final_ending = false;
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        report.lines += 1;
+        let (content, line_ending) = split_line_ending(&line);
+        if line_ending == b"\r\n" {
+            ending = b"\r\n";
+        }
+        final_ending = !line_ending.is_empty();
+        // Sorting needs the text, the line can't be passed through as it is
+        match std::str::from_utf8(content) {
+            Ok(content) => lines.push(content.to_string()),
+            Err(e) => {
+                return Err(Error::transform(format!(
+                    "{}:{}: invalid UTF-8: {}, the whole input stages need text",
+                    name, report.lines, e
+                )))
+            }
+        }
+    }
+
+    // An error of a whole input stage, like an unknown sort order, fails the file
+    let mut failures = Vec::new();
+    let lines = pipeline.run_lines(lines, options, |i, e| failures.push((i, e)))?;
+    for (i, e) in failures {
+        report.failed += 1;
+        match i {
+            Some(i) => writeln!(errors, "{}:{}: {}, line left unchanged", name, i + 1, e)?,
+            None => writeln!(
+                errors,
+                "{}: {}, on a line made by a whole input stage, kept as that stage wrote it",
+                name, e
+            )?,
+        }
+    }
+    for (i, output) in lines.iter().enumerate() {
+        match uniquifier.as_deref_mut() {
+            Some(uniquifier) => writer.write_all(uniquifier.unique(output).as_bytes())?,
+            None => writer.write_all(output.as_bytes())?,
+        }
+        if i + 1 < lines.len() || final_ending {
+            writer.write_all(ending)?;
+        }
+    }
+    Ok(report)
+}
+
+fn open_input(path: &str) -> io::Result<Box<dyn BufRead>> {
+    if path == "-" {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+// A new file next to `path`, the name is not used by any other file or run
+fn create_temporary(path: &str) -> Result<(String, File), Error> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    loop {
+        let temporary = format!(
+            "{}.{}-{}.homework-04.tmp",
+            path,
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        );
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temporary)
+        {
+            Ok(file) => return Ok((temporary, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(Error::io(&temporary, e)),
+        }
+    }
+}
+
+// Rewrites the file through a temporary file, the original is kept with
+// the backup suffix unless the suffix is empty. When processing fails the
+// temporary file is removed and the original is left as it was.
+fn process_in_place(
+    path: &str,
+    backup_suffix: &str,
+    pipeline: &Pipeline,
+    options: &Options,
+    uniquifier: Option<&mut Uniquifier>,
+) -> Result<BatchReport, Error> {
+    // A missing input leaves no temporary file behind
+    let input = open_input(path).map_err(|e| Error::io(path, e))?;
+    let permissions = fs::metadata(path)
+        .map_err(|e| Error::io(path, e))?
+        .permissions();
+    let (temporary, file) = create_temporary(path)?;
+    let mut writer = BufWriter::new(file);
+    let result = process_lines(
+        path,
+        input,
+        &mut writer,
+        &mut io::stderr(),
+        pipeline,
+        options,
+        uniquifier,
+    )
+    .and_then(|report| Ok(writer.flush().map(|_| report)?))
+    .map_err(|e| e.with_path(&temporary));
+    drop(writer);
+    let result = result.and_then(|report| {
+        fs::set_permissions(&temporary, permissions).map_err(|e| Error::io(&temporary, e))?;
+        Ok(report)
+    });
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            // Nothing more can be done when the removal fails too
+            let _ = fs::remove_file(&temporary);
+            return Err(e);
+        }
+    };
+    if !backup_suffix.is_empty() {
+        let backup = format!("{}{}", path, backup_suffix);
+        fs::rename(path, &backup).map_err(|e| Error::io(backup, e))?;
+    }
+    fs::rename(&temporary, path).map_err(|e| Error::io(path, e))?;
+    Ok(report)
+}
+
+pub fn run_batch(pipeline: &Pipeline, options: &Options) -> Result<BatchReport, Error> {
+    let inputs: Vec<&str> = options.get_all("in").collect();
+    let mut report = BatchReport::default();
+    // Results are unique across all the files
+    let mut uniquifier = uniquifier(pipeline, options);
+
+    if options.flag("in-place") {
+        if options.get("out").is_some() || inputs.contains(&"-") {
+            return Err(Error::invalid_argument(
+                "--in-place can't be combined with --out or reading stdin",
+            ));
+        }
+        let backup_suffix = options.get("backup-suffix").unwrap_or(".bak");
+        for path in inputs {
+            report.add(process_in_place(
+                path,
+                backup_suffix,
+                pipeline,
+                options,
+                uniquifier.as_mut(),
+            )?);
+        }
+        return Ok(report);
+    }
+
+    // Creating the output truncates it before the input is read
+    if let Some(out) = options.get("out") {
+        if let Ok(out) = fs::canonicalize(out) {
+            let same = inputs
+                .iter()
+                .find(|path| fs::canonicalize(path).is_ok_and(|path| path == out));
+            if let Some(path) = same {
+                return Err(Error::invalid_argument(format!(
+                    "--out {} is also an input, use --in-place to rewrite it",
+                    path
+                )));
+            }
+        }
+    }
+    let mut writer: Box<dyn Write> = match options.get("out") {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| Error::io(path, e))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    for path in inputs {
+        let name = if path == "-" { "<stdin>" } else { path };
+        let input = open_input(path).map_err(|e| Error::io(path, e))?;
+        report.add(process_lines(
+            name,
+            input,
+            &mut writer,
+            &mut io::stderr(),
+            pipeline,
+            options,
+            uniquifier.as_mut(),
+        )?);
+    }
+    writer.flush()?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(input: &[u8], pipeline: &str) -> (Vec<u8>, BatchReport) {
+        let (output, report, _) = process_reporting(input, pipeline);
+        (output, report)
+    }
+
+    // Also gives the reported errors
+    fn process_reporting(input: &[u8], pipeline: &str) -> (Vec<u8>, BatchReport, String) {
+        let pipeline: Pipeline = pipeline.parse().unwrap();
+        let options = Options::new();
+        let mut uniquifier = uniquifier(&pipeline, &options);
+        let mut output = Vec::new();
+        let mut errors = Vec::new();
+        let report = process_lines(
+            "test",
+            input,
+            &mut output,
+            &mut errors,
+            &pipeline,
+            &options,
+            uniquifier.as_mut(),
+        )
+        .unwrap();
+        (output, report, String::from_utf8(errors).unwrap())
+    }
+
+    #[test]
+    fn keeps_line_endings() {
+        let (output, report) = process(b"Hello World\r\nFoo Bar\nlast", "slugify");
+        assert_eq!(output, b"hello-world\r\nfoo-bar\nlast");
+        assert_eq!(
+            report,
+            BatchReport {
+                lines: 3,
+                failed: 0
+            }
+        );
+    }
+
+    #[test]
+    fn unique_results_get_a_number() {
+        let (output, _) = process(b"Hello World\nhello world!\nHello World\n", "slugify");
+        assert_eq!(output, b"hello-world\nhello-world\nhello-world\n");
+        let (output, _) = process(
+            b"Hello World\nhello world!\nHello World\n",
+            "slugify:unique,separator=_",
+        );
+        assert_eq!(output, b"hello_world\nhello_world_2\nhello_world_3\n");
+    }
+
+    #[test]
+    fn filtered_lines_are_dropped() {
+        let (output, report) = process(b"apple\nbanana\r\navocado", "matches:pattern=^a|upper");
+        assert_eq!(output, b"APPLE\nAVOCADO");
+        assert_eq!(report.lines, 3);
+    }
+
+    #[test]
+    fn whole_input_stages_get_all_the_lines() {
+        let (output, report) = process(
+            b"pear\r\napple\r\n\r\npear\r\n",
+            "upper|dedupe|sort-lines:reverse|nl",
+        );
+        assert_eq!(output, b"1 PEAR\r\n2 APPLE\r\n3 \r\n");
+        assert_eq!(report.lines, 4);
+
+        let (output, _) = process(b"b\na\nb", "sort-lines|slugify:unique");
+        assert_eq!(output, b"a\nb\nb-2");
+    }
+
+    #[test]
+    fn whole_input_stage_errors_fail_the_file() {
+        let pipeline: Pipeline = "sort-lines:order=random".parse().unwrap();
+        let mut output = Vec::new();
+        let options = Options::new();
+        let err = process_lines(
+            "test",
+            &b"b\na\n"[..],
+            &mut output,
+            &mut Vec::new(),
+            &pipeline,
+            &options,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), "invalid_argument");
+        assert_eq!(output, b"");
+
+        // The file rewritten in place keeps its content
+        let directory = std::env::temp_dir().join("homework-04-batch-in-place");
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("x.txt");
+        fs::write(&path, "b\na\n").unwrap();
+        let path = path.to_str().unwrap();
+        let err = process_in_place(path, "", &pipeline, &options, None).unwrap_err();
+        assert_eq!(err.kind(), "invalid_argument");
+        assert_eq!(fs::read_to_string(path).unwrap(), "b\na\n");
+        assert_eq!(file_names(&directory), ["x.txt"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    fn file_names(directory: &std::path::Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rewritten_files_keep_their_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = std::env::temp_dir().join("homework-04-batch-permissions");
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("script.sh");
+        fs::write(&path, "echo hi\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o751)).unwrap();
+        let pipeline: Pipeline = "upper".parse().unwrap();
+        let path = path.to_str().unwrap();
+        process_in_place(path, ".bak", &pipeline, &Options::new(), None).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "ECHO HI\n");
+        let mode = fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o751);
+        assert_eq!(file_names(&directory), ["script.sh", "script.sh.bak"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn errors_name_the_line_of_the_file() {
+        let (output, report, errors) = process_reporting(b"YQ==\n!\nYg==\n", "sort-lines|b64dec");
+        assert_eq!(output, b"!\na\nb\n");
+        assert_eq!(report.failed, 1);
+        assert!(errors.starts_with("test:2: "), "{}", errors);
+        assert!(errors.ends_with(", line left unchanged\n"), "{}", errors);
+
+        // `uniq:count` makes lines which are not in the file
+        let (output, _, errors) = process_reporting(b"x\nx\n", "uniq:count|b64dec");
+        assert_eq!(output, b"      2 x\n");
+        assert!(errors.starts_with("test: "), "{}", errors);
+        assert!(errors.contains("kept as that stage wrote it"), "{}", errors);
+    }
+
+    #[test]
+    fn output_is_not_one_of_the_inputs() {
+        let directory = std::env::temp_dir().join("homework-04-batch-out");
+        fs::create_dir_all(&directory).unwrap();
+        let input = directory.join("in.txt");
+        fs::write(&input, "hello\n").unwrap();
+        let input = input.to_str().unwrap();
+        let pipeline: Pipeline = "upper".parse().unwrap();
+
+        let mut options = Options::new();
+        options.push("in", input);
+        options.push(
+            "out",
+            &format!("{}/../homework-04-batch-out/in.txt", directory.display()),
+        );
+        let err = run_batch(&pipeline, &options).unwrap_err();
+        assert_eq!(err.kind(), "invalid_argument");
+        assert_eq!(fs::read_to_string(input).unwrap(), "hello\n");
+
+        let missing = directory.join("missing.txt");
+        let mut options = Options::from_spec("in-place").unwrap();
+        options.push("in", missing.to_str().unwrap());
+        let err = run_batch(&pipeline, &options).unwrap_err();
+        assert_eq!(err.kind(), "io");
+        assert_eq!(file_names(&directory), ["in.txt"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn invalid_lines_are_left_unchanged() {
+        let (output, report, errors) = process_reporting(b"ok\n\xff\xfe bad\nfine\n", "uppercase");
+        assert_eq!(output, b"OK\n\xff\xfe bad\nFINE\n");
+        assert_eq!(
+            report,
+            BatchReport {
+                lines: 3,
+                failed: 1
+            }
+        );
+        assert!(errors.starts_with("test:2: invalid UTF-8"), "{}", errors);
+    }
+
+    #[test]
+    fn invalid_lines_fail_whole_input_stages() {
+        let directory = std::env::temp_dir().join("homework-04-batch-invalid");
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("x.txt");
+        fs::write(&path, b"b\n\xff\na\n").unwrap();
+        let pipeline: Pipeline = "sort-lines".parse().unwrap();
+        let err = process_in_place(path.to_str().unwrap(), "", &pipeline, &Options::new(), None)
+            .unwrap_err();
+        assert_eq!(err.kind(), "transform");
+        assert!(
+            err.to_string().contains("x.txt:2: invalid UTF-8"),
+            "{}",
+            err
+        );
+        assert_eq!(fs::read(&path).unwrap(), b"b\n\xff\na\n");
+        assert_eq!(file_names(&directory), ["x.txt"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+//! Splitting identifiers into words for the case conversions.
+//!
+//! Words end at anything which is not a letter or a digit, at camel humps
+//! (`fooBar`), at the end of an acronym (`HTTPServer`) and after a number
+//! followed by an uppercase letter (`Server2Config`). Digits stay with the
+//! word before them.
+//!
+//! `HTTPServer2Config` --> `[HTTP, Server2, Config]`
+
+use crate::locale::Locale;
+
+/// Splits `input` into words, keeping the case of the letters.
+pub fn words(input: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+
+    for part in input.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<(usize, char)> = part.char_indices().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (index, c) = chars[i];
+            let previous = chars[i - 1].1;
+            let next = chars.get(i + 1).map(|&(_, c)| c);
+            let hump = c.is_uppercase() && (previous.is_lowercase() || previous.is_numeric());
+            let acronym_end =
+                c.is_uppercase() && previous.is_uppercase() && next.is_some_and(char::is_lowercase);
+            if hump || acronym_end {
+                words.push(&part[start..index]);
+                start = index;
+            }
+        }
+        if start < part.len() {
+            words.push(&part[start..]);
+        }
+    }
+    words
+}
+
+/// `hELLO` --> `Hello`
+pub fn capitalize(word: &str) -> String {
+    Locale::root().capitalize(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_humps_acronyms_and_digits() {
+        assert_eq!(words("HTTPServer2Config"), ["HTTP", "Server2", "Config"]);
+        assert_eq!(words("parseJSONFile"), ["parse", "JSON", "File"]);
+        assert_eq!(words("http_server2_config"), ["http", "server2", "config"]);
+        assert_eq!(words("  Hello, World! "), ["Hello", "World"]);
+        assert_eq!(words("žluťoučkýKůň"), ["žluťoučký", "Kůň"]);
+        assert!(words("--").is_empty());
+    }
+
+    #[test]
+    fn capitalizes_the_first_letter_only() {
+        assert_eq!(capitalize("hELLO"), "Hello");
+        assert_eq!(capitalize("ž"), "Ž");
+        assert_eq!(capitalize(""), "");
+    }
+}
@@ -0,0 +1,176 @@
+//! Unicode normalization and cleanup of user submitted text.
+//!
+//! The same text can be written in several ways: `é` as one code point or as
+//! `e` with a combining accent, a space as U+0020 or as a non-breaking
+//! space, an apostrophe as `'` or `’`. The functions here turn these into
+//! one form so the text can be compared and searched.
+
+use crate::error::Error;
+use deunicode::deunicode_char;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+const ZERO_WIDTH_NON_JOINER: char = '\u{200c}';
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+/// Composed form, `e` + U+0301 --> `é`
+pub fn nfc(input: &str) -> Result<String, Error> {
+    Ok(input.nfc().collect())
+}
+
+/// Decomposed form, `é` --> `e` + U+0301
+pub fn nfd(input: &str) -> Result<String, Error> {
+    Ok(input.nfd().collect())
+}
+
+/// Composed compatibility form, `ﬁ²` --> `fi2`
+pub fn nfkc(input: &str) -> Result<String, Error> {
+    Ok(input.nfkc().collect())
+}
+
+/// Decomposed compatibility form
+pub fn nfkd(input: &str) -> Result<String, Error> {
+    Ok(input.nfkd().collect())
+}
+
+/// Strips the accents and transliterates what is left to ASCII,
+/// `Žluťoučký Straße` --> `Zlutoucky Strasse`
+pub fn ascii_fold(input: &str) -> Result<String, Error> {
+    Ok(input
+        .nfkd()
+        .filter(|&c| !is_combining_mark(c))
+        .map(|c| {
+            if c.is_ascii() {
+                c.to_string()
+            } else {
+                deunicode_char(c).unwrap_or("").to_string()
+            }
+        })
+        .collect())
+}
+
+// Characters which take no space and are never needed in the text
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{ad}'                      // soft hyphen
+        | '\u{180e}'                  // Mongolian vowel separator
+        | '\u{200b}'                  // zero width space
+        | '\u{200e}' | '\u{200f}'     // left-to-right and right-to-left marks
+        | '\u{202a}'..='\u{202e}'     // bidi embeddings and overrides
+        | '\u{2060}'..='\u{2064}'     // word joiner and invisible operators
+        | '\u{2066}'..='\u{2069}'     // bidi isolates
+        | '\u{feff}' // byte order mark
+    )
+}
+
+/// Removes zero width characters, soft hyphens, bidi controls and the byte
+/// order mark.
+///
+/// Zero width joiners and non-joiners are kept between two letters or
+/// symbols of a non-Latin script, where they change how the text is drawn,
+/// e.g. in emoji sequences like 👨‍👩‍👧 or in Persian.
+pub fn strip_invisible(input: &str) -> Result<String, Error> {
+    let chars: Vec<char> = input.chars().collect();
+    let joins = |i: usize| {
+        let visible = |c: Option<&char>| c.is_some_and(|&c| !c.is_ascii() && !c.is_whitespace());
+        i > 0 && visible(chars.get(i - 1)) && visible(chars.get(i + 1))
+    };
+    Ok(chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| match c {
+            ZERO_WIDTH_JOINER | ZERO_WIDTH_NON_JOINER => joins(i),
+            _ => !is_invisible(c),
+        })
+        .map(|(_, &c)| c)
+        .collect())
+}
+
+/// Turns every kind of Unicode whitespace into a plain space, collapses
+/// runs of it and trims both ends. With `keep_newlines` the lines are
+/// cleaned up separately and kept.
+///
+/// `\u{a0} a\u{2003}\t b ` --> `a b`
+pub fn normalize_whitespace(input: &str, keep_newlines: bool) -> Result<String, Error> {
+    let collapse = |s: &str| s.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if keep_newlines {
+        Ok(input
+            .lines()
+            .map(collapse)
+            .collect::<Vec<String>>()
+            .join("\n"))
+    } else {
+        Ok(collapse(input))
+    }
+}
+
+/// Typographic quotes, apostrophes and primes as ASCII quotes,
+/// `„Don’t“ «stop»` --> `"Don't" "stop"`
+pub fn ascii_quotes(input: &str) -> Result<String, Error> {
+    Ok(input
+        .chars()
+        .map(|c| match c {
+            '‘' | '’' | '‚' | '‛' | '′' | '‹' | '›' | 'ʼ' => '\'',
+            '“' | '”' | '„' | '‟' | '″' | '«' | '»' | '〝' | '〞' => '"',
+            _ => c,
+        })
+        .collect())
+}
+
+/// Removes all Unicode whitespace, not only spaces,
+/// `a\u{a0}b\tc` --> `abc`
+pub fn no_whitespace(input: &str) -> Result<String, Error> {
+    Ok(input.chars().filter(|c| !c.is_whitespace()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalization_forms() {
+        let composed = "\u{e9}";
+        let decomposed = "e\u{301}";
+        assert_eq!(nfc(decomposed).unwrap(), composed);
+        assert_eq!(nfd(composed).unwrap(), decomposed);
+        assert_eq!(nfkc("ﬁ²").unwrap(), "fi2");
+        assert_eq!(nfkd("ﬁé").unwrap(), "fie\u{301}");
+    }
+
+    #[test]
+    fn folds_to_ascii() {
+        assert_eq!(
+            ascii_fold("Žluťoučký Straße e\u{301}").unwrap(),
+            "Zlutoucky Strasse e"
+        );
+        assert_eq!(ascii_fold("Ærøskøbing ﬁ").unwrap(), "AEroskobing fi");
+    }
+
+    #[test]
+    fn strips_invisible_characters() {
+        assert_eq!(
+            strip_invisible("\u{feff}zero\u{200b}width\u{ad} \u{200d}x\u{202e}").unwrap(),
+            "zerowidth x"
+        );
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(strip_invisible(family).unwrap(), family);
+        assert_eq!(strip_invisible("a\u{200c}b").unwrap(), "ab");
+    }
+
+    #[test]
+    fn whitespace() {
+        let input = "\u{a0} a\u{2003}\t b \n\u{3000}c  d\u{202f}";
+        assert_eq!(normalize_whitespace(input, false).unwrap(), "a b c d");
+        assert_eq!(normalize_whitespace(input, true).unwrap(), "a b\nc d");
+        assert_eq!(no_whitespace("a\u{a0}b\tc\u{2009}d e").unwrap(), "abcde");
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(
+            ascii_quotes("„Don’t“ «stop» ‘now’").unwrap(),
+            "\"Don't\" \"stop\" 'now'"
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Rendering of CSV files as tables and other formats.
+
+mod query;
+mod render;
+mod stream;
+
+pub use query::{Column, Comparison, Filter, Query, SortKey};
+pub use render::{
+    renderer_for, Csv, Html, Json, JsonLines, Markdown, Renderer, Table, Tsv, OUTPUT_FORMATS,
+};
+pub use stream::{stream_table, StreamOptions};
+
+use crate::error::Error;
+use crate::options::Options;
+use crate::width::{display_width, pad, Align};
+use csv::StringRecord;
+use std::fmt;
+use std::io;
+
+/// Parsed CSV file, printed as a table through [`fmt::Display`].
+#[derive(Debug, Clone)]
+pub struct CsvRecords {
+    pub headers: StringRecord,
+    pub records: Vec<csv::StringRecord>,
+}
+
+impl fmt::Display for CsvRecords {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut column_widths = vec![0; self.headers.len()];
+
+        for (i, header) in self.headers.iter().enumerate() {
+            column_widths[i] = display_width(header);
+        }
+
+        for record in &self.records {
+            for (i, field) in record.iter().enumerate() {
+                column_widths[i] = column_widths[i].max(display_width(field));
+            }
+        }
+
+        let alignments: Vec<Align> = (0..self.headers.len())
+            .map(|i| {
+                if self.is_numeric_column(i) {
+                    Align::Right
+                } else {
+                    Align::Left
+                }
+            })
+            .collect();
+
+        let format_row = |row: &StringRecord| {
+            row.iter()
+                .enumerate()
+                .map(|(i, field)| pad(field, column_widths[i], alignments[i]))
+                .collect::<Vec<String>>()
+                .join(" | ")
+        };
+
+        writeln!(f, "{}", format_row(&self.headers))?;
+        writeln!(
+            f,
+            "{}",
+            column_widths
+                .iter()
+                .map(|&w| "-".repeat(w))
+                .collect::<Vec<String>>()
+                .join("-+-")
+        )?;
+
+        // Print records
+        for record in &self.records {
+            writeln!(f, "{}", format_row(record))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CsvRecords {
+    /// True when every non empty field of the column is a number, see
+    /// [`is_numeric_column`].
+    pub fn is_numeric_column(&self, column: usize) -> bool {
+        is_numeric_column(&self.records, column)
+    }
+
+    /// Reads all the records, the first row is the header.
+    pub fn from_reader<R: io::Read>(reader: &mut csv::Reader<R>) -> Result<CsvRecords, Error> {
+        let mut records: Vec<csv::StringRecord> = Vec::new();
+
+        for result in reader.records() {
+            let record = result?;
+            records.push(record);
+        }
+        let headers = reader.headers()?.clone();
+        Ok(CsvRecords { headers, records })
+    }
+}
+
+/// True when the column has a non empty field and every non empty field is
+/// a number. Such columns are aligned right by the table and the streaming
+/// renderer.
+pub fn is_numeric_column(records: &[StringRecord], column: usize) -> bool {
+    let mut fields = records
+        .iter()
+        .filter_map(|record| record.get(column))
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .peekable();
+    fields.peek().is_some() && fields.all(|field| field.parse::<f64>().is_ok())
+}
+
+fn process_csv_from_reader<R: io::Read>(
+    reader: &mut csv::Reader<R>,
+    options: &Options,
+) -> Result<String, Error> {
+    let renderer = renderer_for(options.get("output").unwrap_or("table"))?;
+    let mut csv_records = CsvRecords::from_reader(reader)?;
+    let query = Query::from_options(options)?;
+
+    if !query.is_empty() {
+        csv_records = csv_records.query(&query)?;
+    }
+    csv_records.render(&*renderer)
+}
+
+/// Reads the CSV file at `file_path` and renders it as a table, or in the
+/// format given by the `output` option.
+///
+/// The `select`, `where`, `sort`, `skip`, `head` and `tail` options are
+/// applied first, see [`Query`].
+pub fn parse_as_csv(file_path: &str, options: &Options) -> Result<String, Error> {
+    if options.flag("stream") {
+        let mut output = Vec::new();
+        write_csv(file_path, options, &mut output)?;
+        return Ok(String::from_utf8_lossy(&output).into_owned());
+    }
+    let mut rdr =
+        csv::Reader::from_path(file_path).map_err(|e| Error::csv(Some(file_path.into()), e))?;
+    process_csv_from_reader(&mut rdr, options).map_err(|e| e.with_path(file_path))
+}
+
+/// Like [`parse_as_csv`], for CSV text instead of a file.
+pub fn render_csv(data: &str, options: &Options) -> Result<String, Error> {
+    let mut rdr = csv::Reader::from_reader(data.as_bytes());
+    process_csv_from_reader(&mut rdr, options)
+}
+
+/// Like [`parse_as_csv`], writing into `out`. With the `stream` option the
+/// file is rendered while it is read, see [`stream_table`].
+pub fn write_csv(file_path: &str, options: &Options, out: &mut dyn io::Write) -> Result<(), Error> {
+    if !options.flag("stream") {
+        writeln!(out, "{}", parse_as_csv(file_path, options)?)?;
+        return Ok(());
+    }
+    if !matches!(options.get("output"), None | Some("table")) {
+        return Err(Error::invalid_argument(
+            "Only the table output can be streamed",
+        ));
+    }
+    let mut rdr =
+        csv::Reader::from_path(file_path).map_err(|e| Error::csv(Some(file_path.into()), e))?;
+    stream_table(
+        &mut rdr,
+        &Query::from_options(options)?,
+        &StreamOptions::from_options(options)?,
+        out,
+    )
+    .map_err(|e| e.with_path(file_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_aligned_table() {
+        let data = "name,age\nAlice,30\nBob,4\n";
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+        let table = process_csv_from_reader(&mut rdr, &Options::new()).unwrap();
+        assert_eq!(
+            table,
+            "name  | age\n------+----\nAlice |  30\nBob   |   4\n"
+        );
+    }
+
+    #[test]
+    fn renders_csv_text() {
+        let options = Options::from_spec("output=markdown").unwrap();
+        assert_eq!(
+            render_csv("n,a\nx,1\n", &options).unwrap(),
+            "| n | a |\n| --- | --- |\n| x | 1 |\n"
+        );
+    }
+
+    #[test]
+    fn numeric_columns() {
+        let records: Vec<StringRecord> = [["1", "a"], [" 2.5 ", ""], ["", "3"]]
+            .iter()
+            .map(|row| StringRecord::from(row.to_vec()))
+            .collect();
+        assert!(is_numeric_column(&records, 0));
+        assert!(!is_numeric_column(&records, 1));
+        assert!(!is_numeric_column(&records, 2));
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let err = parse_as_csv("/does/not/exist.csv", &Options::new()).unwrap_err();
+        assert!(
+            matches!(err, Error::Io { path: Some(ref path), .. } if path.ends_with("exist.csv"))
+        );
+    }
+
+    #[test]
+    fn applies_query_options() {
+        let data = "name,age\nAlice,30\nBob,4\n";
+        let mut rdr = csv::Reader::from_reader(data.as_bytes());
+        let options = Options::from_spec("select=name,where=age<10").unwrap();
+        let table = process_csv_from_reader(&mut rdr, &options).unwrap();
+        assert_eq!(table, "name\n----\nBob \n");
+    }
+}
@@ -0,0 +1,420 @@
+//! Selecting, filtering and sorting the rows of [`CsvRecords`].
+//!
+//! Columns are referred to by their header name or by their 1-based index.
+//! Values which both parse as numbers are compared as numbers.
+
+use super::CsvRecords;
+use crate::error::Error;
+use crate::options::Options;
+use csv::StringRecord;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// Column given by header name or by 1-based index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl Column {
+    /// Position of the column in `headers`. A header called `2` wins over
+    /// the second column.
+    pub fn resolve(&self, headers: &StringRecord) -> Result<usize, Error> {
+        let name = match self {
+            Column::Name(name) => name.clone(),
+            Column::Index(index) => index.to_string(),
+        };
+        if let Some(position) = headers.iter().position(|header| header == name) {
+            return Ok(position);
+        }
+        match self {
+            Column::Name(name) => Err(Error::invalid_argument(format!(
+                "Unknown column '{}'",
+                name
+            ))),
+            Column::Index(index) if (1..=headers.len()).contains(index) => Ok(index - 1),
+            Column::Index(index) => Err(Error::invalid_argument(format!(
+                "Column index {} out of range, the file has {} columns",
+                index,
+                headers.len()
+            ))),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(Error::invalid_argument("Column name is empty"));
+        }
+        Ok(match s.parse::<usize>() {
+            Ok(index) => Column::Index(index),
+            Err(_) => Column::Name(s.to_string()),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Matches(Regex),
+    NotMatches(Regex),
+}
+
+/// Row predicate like `age>30`, `city=Prague` or `name~^A`.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub column: Column,
+    pub comparison: Comparison,
+    pub value: String,
+}
+
+// Two character operators first, so `>=` is not read as `>`
+const OPERATORS: [&str; 8] = ["!=", ">=", "<=", "!~", "=", ">", "<", "~"];
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (position, operator) = s
+            .char_indices()
+            .find_map(|(i, _)| {
+                OPERATORS
+                    .iter()
+                    .find(|op| s[i..].starts_with(*op))
+                    .map(|op| (i, *op))
+            })
+            .ok_or_else(|| {
+                Error::invalid_argument(format!(
+                    "Invalid filter '{}', expected <column><operator><value> with one of: {}",
+                    s,
+                    OPERATORS.join(" ")
+                ))
+            })?;
+
+        let column = s[..position].parse()?;
+        let value = s[position + operator.len()..].trim().to_string();
+        let regex = || {
+            Regex::new(&value)
+                .map_err(|e| Error::invalid_argument(format!("Invalid regex in '{}': {}", s, e)))
+        };
+        let comparison = match operator {
+            "!=" => Comparison::NotEqual,
+            ">=" => Comparison::GreaterOrEqual,
+            "<=" => Comparison::LessOrEqual,
+            "!~" => Comparison::NotMatches(regex()?),
+            "=" => Comparison::Equal,
+            ">" => Comparison::Greater,
+            "<" => Comparison::Less,
+            _ => Comparison::Matches(regex()?),
+        };
+        Ok(Filter {
+            column,
+            comparison,
+            value,
+        })
+    }
+}
+
+impl Filter {
+    fn matches(&self, field: &str) -> bool {
+        let ordering = compare_values(field, &self.value);
+        match &self.comparison {
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::NotEqual => ordering != Ordering::Equal,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Matches(regex) => regex.is_match(field),
+            Comparison::NotMatches(regex) => !regex.is_match(field),
+        }
+    }
+}
+
+/// Sort column, `-age` sorts descending, `age` or `+age` ascending.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub column: Column,
+    pub descending: bool,
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (column, descending) = match s.strip_prefix('-') {
+            Some(column) => (column, true),
+            None => (s.strip_prefix('+').unwrap_or(s), false),
+        };
+        Ok(SortKey {
+            column: column.parse()?,
+            descending,
+        })
+    }
+}
+
+/// Which columns to show, which rows to keep and how to order them.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub select: Vec<Column>,
+    pub filters: Vec<Filter>,
+    pub sort: Vec<SortKey>,
+    /// Rows dropped from the start, after filtering and sorting.
+    pub skip: usize,
+    /// Keep only the first rows after `skip`.
+    pub head: Option<usize>,
+    /// Keep only the last rows after `head`.
+    pub tail: Option<usize>,
+}
+
+impl Query {
+    /// Reads the `select`, `where` (repeatable), `sort`, `skip`, `head` and
+    /// `tail` options.
+    ///
+    /// `select` and `sort` are comma separated lists.
+    pub fn from_options(options: &Options) -> Result<Query, Error> {
+        let list = |key| {
+            options
+                .get(key)
+                .map(|value| value.split(',').collect::<Vec<_>>())
+                .unwrap_or_default()
+        };
+        Ok(Query {
+            select: list("select")
+                .into_iter()
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+            filters: options
+                .get_all("where")
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+            sort: list("sort")
+                .into_iter()
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+            skip: options.parse("skip")?.unwrap_or(0),
+            head: options.parse("head")?,
+            tail: options.parse("tail")?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.select.is_empty()
+            && self.filters.is_empty()
+            && self.sort.is_empty()
+            && self.skip == 0
+            && self.head.is_none()
+            && self.tail.is_none()
+    }
+
+    /// Resolves the columns against the headers of a file.
+    pub(crate) fn plan(&self, headers: &StringRecord) -> Result<Plan<'_>, Error> {
+        Ok(Plan {
+            filters: self
+                .filters
+                .iter()
+                .map(|filter| Ok((filter.column.resolve(headers)?, filter)))
+                .collect::<Result<_, Error>>()?,
+            sort: self
+                .sort
+                .iter()
+                .map(|key| Ok((key.column.resolve(headers)?, key.descending)))
+                .collect::<Result<_, Error>>()?,
+            select: self
+                .select
+                .iter()
+                .map(|column| column.resolve(headers))
+                .collect::<Result<_, Error>>()?,
+        })
+    }
+}
+
+/// [`Query`] with the columns turned into positions.
+pub(crate) struct Plan<'a> {
+    filters: Vec<(usize, &'a Filter)>,
+    sort: Vec<(usize, bool)>,
+    select: Vec<usize>,
+}
+
+impl Plan<'_> {
+    pub(crate) fn keep(&self, record: &StringRecord) -> bool {
+        self.filters
+            .iter()
+            .all(|(i, filter)| filter.matches(record.get(*i).unwrap_or("")))
+    }
+
+    pub(crate) fn project(&self, record: &StringRecord) -> StringRecord {
+        if self.select.is_empty() {
+            record.clone()
+        } else {
+            self.select
+                .iter()
+                .map(|&i| record.get(i).unwrap_or(""))
+                .collect()
+        }
+    }
+
+    pub(crate) fn is_sorted(&self) -> bool {
+        !self.sort.is_empty()
+    }
+
+    fn compare(&self, a: &StringRecord, b: &StringRecord) -> Ordering {
+        self.sort
+            .iter()
+            .map(|&(i, descending)| {
+                let ordering = compare_values(a.get(i).unwrap_or(""), b.get(i).unwrap_or(""));
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Compares as numbers when both values are numbers, as strings otherwise.
+pub fn compare_values(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+impl CsvRecords {
+    /// New records with the rows filtered, sorted, sliced and the columns
+    /// selected.
+    pub fn query(&self, query: &Query) -> Result<CsvRecords, Error> {
+        let plan = query.plan(&self.headers)?;
+
+        let mut records: Vec<&StringRecord> = self
+            .records
+            .iter()
+            .filter(|record| plan.keep(record))
+            .collect();
+
+        // Stable sort, rows which are equal keep the order from the file
+        records.sort_by(|a, b| plan.compare(a, b));
+
+        let mut records: Vec<&StringRecord> = records
+            .into_iter()
+            .skip(query.skip)
+            .take(query.head.unwrap_or(usize::MAX))
+            .collect();
+        if let Some(tail) = query.tail {
+            records.drain(..records.len().saturating_sub(tail));
+        }
+
+        Ok(CsvRecords {
+            headers: plan.project(&self.headers),
+            records: records
+                .into_iter()
+                .map(|record| plan.project(record))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn people() -> CsvRecords {
+        let data =
+            "name,age,city\nAlice,30,Prague\nBob,4,Brno\nCyril,100,Prague\nDana,30,Ostrava\n";
+        CsvRecords::from_reader(&mut csv::Reader::from_reader(data.as_bytes())).unwrap()
+    }
+
+    fn column(records: &CsvRecords, i: usize) -> Vec<&str> {
+        records.records.iter().map(|r| r.get(i).unwrap()).collect()
+    }
+
+    #[test]
+    fn filters_rows_with_numeric_comparison() {
+        let query = Query {
+            filters: vec!["age>=30".parse().unwrap(), "city=Prague".parse().unwrap()],
+            ..Query::default()
+        };
+        let result = people().query(&query).unwrap();
+        assert_eq!(column(&result, 0), ["Alice", "Cyril"]);
+
+        let query = Query {
+            filters: vec!["name~^[AB]".parse().unwrap()],
+            ..Query::default()
+        };
+        assert_eq!(
+            column(&people().query(&query).unwrap(), 0),
+            ["Alice", "Bob"]
+        );
+    }
+
+    #[test]
+    fn sorts_by_multiple_columns() {
+        let query = Query {
+            sort: vec!["-age".parse().unwrap(), "name".parse().unwrap()],
+            ..Query::default()
+        };
+        let result = people().query(&query).unwrap();
+        assert_eq!(column(&result, 0), ["Cyril", "Alice", "Dana", "Bob"]);
+    }
+
+    #[test]
+    fn selects_columns_by_name_and_index() {
+        let query = Query {
+            select: vec!["city".parse().unwrap(), "1".parse().unwrap()],
+            ..Query::default()
+        };
+        let result = people().query(&query).unwrap();
+        assert_eq!(result.headers, vec!["city", "name"]);
+        assert_eq!(result.records[1], vec!["Brno", "Bob"]);
+    }
+
+    #[test]
+    fn slices_rows_after_sorting() {
+        let query = Query {
+            sort: vec!["name".parse().unwrap()],
+            skip: 1,
+            head: Some(2),
+            ..Query::default()
+        };
+        assert_eq!(
+            column(&people().query(&query).unwrap(), 0),
+            ["Bob", "Cyril"]
+        );
+
+        let query = Query {
+            tail: Some(2),
+            ..Query::default()
+        };
+        assert_eq!(
+            column(&people().query(&query).unwrap(), 0),
+            ["Cyril", "Dana"]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_columns_and_bad_filters() {
+        let query = Query {
+            select: vec!["salary".parse().unwrap()],
+            ..Query::default()
+        };
+        let err = people().query(&query).unwrap_err();
+        assert_eq!(err.kind(), "invalid_argument");
+        assert_eq!(err.to_string(), "Unknown column 'salary'");
+        assert!("age".parse::<Filter>().is_err());
+        assert!("name~(".parse::<Filter>().is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Output formats for [`CsvRecords`].
+//!
+//! The format is selected with the `output` option, e.g. `--output markdown`.
+
+use super::CsvRecords;
+use crate::error::Error;
+use csv::StringRecord;
+use serde_json::{Map, Value};
+
+/// Names accepted by [`renderer_for`].
+pub const OUTPUT_FORMATS: [&str; 7] = ["table", "markdown", "json", "jsonl", "html", "tsv", "csv"];
+
+/// Turns the records into text.
+pub trait Renderer {
+    fn render(&self, records: &CsvRecords) -> Result<String, Error>;
+}
+
+/// Plain text table, same as the [`std::fmt::Display`] output.
+pub struct Table;
+
+/// GitHub flavored Markdown table.
+pub struct Markdown;
+
+/// JSON array with one object per row, keyed by header.
+pub struct Json;
+
+/// One JSON object per line.
+pub struct JsonLines;
+
+/// HTML `<table>`.
+pub struct Html;
+
+/// Tab separated values, tabs and newlines inside fields are escaped.
+pub struct Tsv;
+
+/// Comma separated values.
+pub struct Csv;
+
+/// Renderer for one of the [`OUTPUT_FORMATS`].
+pub fn renderer_for(format: &str) -> Result<Box<dyn Renderer>, Error> {
+    match format {
+        "table" => Ok(Box::new(Table)),
+        "markdown" | "md" => Ok(Box::new(Markdown)),
+        "json" => Ok(Box::new(Json)),
+        "jsonl" | "json-lines" => Ok(Box::new(JsonLines)),
+        "html" => Ok(Box::new(Html)),
+        "tsv" => Ok(Box::new(Tsv)),
+        "csv" => Ok(Box::new(Csv)),
+        _ => Err(Error::invalid_argument(format!(
+            "Unknown output format: {}\nAvailable formats: {}",
+            format,
+            OUTPUT_FORMATS.join(", ")
+        ))),
+    }
+}
+
+impl CsvRecords {
+    pub fn render(&self, renderer: &dyn Renderer) -> Result<String, Error> {
+        renderer.render(self)
+    }
+
+    // Rows as JSON objects keyed by header, in the column order of the file
+    fn json_rows(&self) -> impl Iterator<Item = Value> + '_ {
+        self.records.iter().map(|record| {
+            let object: Map<String, Value> = self
+                .headers
+                .iter()
+                .enumerate()
+                .map(|(i, header)| {
+                    let field = record.get(i).unwrap_or("");
+                    (header.to_string(), Value::String(field.to_string()))
+                })
+                .collect();
+            Value::Object(object)
+        })
+    }
+}
+
+impl Renderer for Table {
+    fn render(&self, records: &CsvRecords) -> Result<String, Error> {
+        Ok(records.to_string())
+    }
+}
+
+impl Renderer for Markdown {
+    fn render(&self, records: &CsvRecords) -> Result<String, Error> {
+        let row = |record: &StringRecord| {
+            let cells: Vec<String> = record
+                .iter()
+                .map(|field| field.replace('|', "\\|").replace('\n', "<br>"))
+                .collect();
+            format!("| {} |\n", cells.join(" | "))
+        };
+
+        let mut output = row(&records.headers);
+        output.push_str(&format!("|{}\n", " --- |".repeat(records.headers.len())));
+        for record in &records.records {
+            output.push_str(&row(record));
+        }
+        Ok(output)
+    }
+}
+
+impl Renderer for Json {
+    fn render(&self, records: &CsvRecords) -> Result<String, Error> {
+        let rows = Value::Array(records.json_rows().collect());
+        let json =
+            serde_json::to_string_pretty(&rows).map_err(|e| Error::transform(e.to_string()))?;
+        Ok(json + "\n")
+    }
+}
+
+impl Renderer for JsonLines {
+    fn render(&self, records: &CsvRecords) -> Result<String, Error> {
+        let mut output = String::new();
+        for row in records.json_rows() {
+            let json = serde_json::to_string(&row).map_err(|e| Error::transform(e.to_string()))?;
+            output.push_str(&json);
+            output.push('\n');
+        }
+        Ok(output)
+    }
+}
+
+impl Renderer for Html {
+    fn render(&self, records: &CsvRecords) -> Result<String, Error> {
+        let row = |record: &StringRecord, cell: &str| {
+            let cells: String = record
+                .iter()
+                .map(|field| {
+                    let field = html_escape::encode_text(field);
+                    format!("<{cell}>{}</{cell}>", field, cell = cell)
+                })
+                .collect();
+            format!("    <tr>{}</tr>\n", cells)
+        };
+
+        let mut output = String::from("<table>\n  <thead>\n");
+        output.push_str(&row(&records.headers, "th"));
+        output.push_str("  </thead>\n  <tbody>\n");
+        for record in &records.records {
+            output.push_str(&row(record, "td"));
+        }
+        output.push_str("  </tbody>\n</table>\n");
+        Ok(output)
+    }
+}
+
+fn escape_tsv(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+impl Renderer for Tsv {
+    fn render(&self, records: &CsvRecords) -> Result<String, Error> {
+        let row = |record: &StringRecord| {
+            let cells: Vec<String> = record.iter().map(escape_tsv).collect();
+            cells.join("\t") + "\n"
+        };
+
+        let mut output = row(&records.headers);
+        for record in &records.records {
+            output.push_str(&row(record));
+        }
+        Ok(output)
+    }
+}
+
+impl Renderer for Csv {
+    fn render(&self, records: &CsvRecords) -> Result<String, Error> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&records.headers)?;
+        for record in &records.records {
+            writer.write_record(record)?;
+        }
+        let bytes = writer
+            .into_inner()
+            .map_err(|e| Error::from(e.into_error()))?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> CsvRecords {
+        let data = "name,note\nAlice,\"a|b, <c>\"\nBob,\"tab\there\"\n";
+        CsvRecords::from_reader(&mut csv::Reader::from_reader(data.as_bytes())).unwrap()
+    }
+
+    fn render(format: &str) -> String {
+        records().render(&*renderer_for(format).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn every_format_has_a_renderer() {
+        for format in OUTPUT_FORMATS {
+            assert!(renderer_for(format).is_ok());
+        }
+        assert!(renderer_for("xml").is_err());
+    }
+
+    #[test]
+    fn markdown() {
+        assert_eq!(
+            render("markdown"),
+            "| name | note |\n| --- | --- |\n| Alice | a\\|b, <c> |\n| Bob | tab\there |\n"
+        );
+    }
+
+    #[test]
+    fn json_keeps_column_order() {
+        assert_eq!(
+            render("jsonl"),
+            "{\"name\":\"Alice\",\"note\":\"a|b, <c>\"}\n{\"name\":\"Bob\",\"note\":\"tab\\there\"}\n"
+        );
+        let parsed: Value = serde_json::from_str(&render("json")).unwrap();
+        assert_eq!(parsed[1]["name"], "Bob");
+    }
+
+    #[test]
+    fn html_escapes_fields() {
+        assert!(render("html").contains("<tr><td>Alice</td><td>a|b, &lt;c&gt;</td></tr>"));
+    }
+
+    #[test]
+    fn tsv_and_csv() {
+        assert_eq!(
+            render("tsv"),
+            "name\tnote\nAlice\ta|b, <c>\nBob\ttab\\there\n"
+        );
+        assert_eq!(
+            render("csv"),
+            "name,note\nAlice,\"a|b, <c>\"\nBob,tab\there\n"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Table rendering which doesn't keep the whole file in memory.
+//!
+//! Column widths are taken from the first `sample` rows, later rows are
+//! printed as they are read and cells wider than their column are cut with
+//! an ellipsis. Only the sample, or the last `tail` rows, are kept in memory.
+
+use super::is_numeric_column;
+use super::query::Query;
+use crate::error::Error;
+use crate::options::Options;
+use crate::width::{display_width, pad, truncate, Align};
+use csv::StringRecord;
+use std::collections::VecDeque;
+use std::io::{self, BufWriter, Write};
+
+/// Settings of the streaming renderer, from the `sample` and `max-width` options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamOptions {
+    /// Number of rows used to find the column widths.
+    pub sample: usize,
+    /// No column is wider than this.
+    pub max_width: usize,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        StreamOptions {
+            sample: 100,
+            max_width: 40,
+        }
+    }
+}
+
+impl StreamOptions {
+    pub fn from_options(options: &Options) -> Result<StreamOptions, Error> {
+        let default = StreamOptions::default();
+        Ok(StreamOptions {
+            sample: options.parse("sample")?.unwrap_or(default.sample),
+            max_width: options.parse("max-width")?.unwrap_or(default.max_width),
+        })
+    }
+}
+
+struct Layout {
+    widths: Vec<usize>,
+    alignments: Vec<Align>,
+}
+
+impl Layout {
+    fn new(headers: &StringRecord, sample: &[StringRecord], max_width: usize) -> Layout {
+        let widths = headers
+            .iter()
+            .enumerate()
+            .map(|(i, header)| {
+                sample
+                    .iter()
+                    .map(|record| display_width(record.get(i).unwrap_or("")))
+                    .chain(std::iter::once(display_width(header)))
+                    .max()
+                    .unwrap_or(0)
+                    .clamp(1, max_width.max(1))
+            })
+            .collect();
+        let alignments = (0..headers.len())
+            .map(|i| {
+                if is_numeric_column(sample, i) {
+                    Align::Right
+                } else {
+                    Align::Left
+                }
+            })
+            .collect();
+        Layout { widths, alignments }
+    }
+
+    fn write_row<W: Write>(&self, out: &mut W, row: &StringRecord) -> io::Result<()> {
+        let cells: Vec<String> = self
+            .widths
+            .iter()
+            .enumerate()
+            .map(|(i, &width)| {
+                let field = truncate(row.get(i).unwrap_or(""), width);
+                pad(&field, width, self.alignments[i])
+            })
+            .collect();
+        writeln!(out, "{}", cells.join(" | "))
+    }
+
+    fn write_header<W: Write>(&self, out: &mut W, headers: &StringRecord) -> io::Result<()> {
+        self.write_row(out, headers)?;
+        let separator: Vec<String> = self.widths.iter().map(|&w| "-".repeat(w)).collect();
+        writeln!(out, "{}", separator.join("-+-"))
+    }
+}
+
+/// Renders the records of `reader` as a table into `out` while reading them.
+///
+/// `select`, `where`, `skip`, `head` and `tail` of the query are applied,
+/// sorting needs all the rows and is refused.
+pub fn stream_table<R: io::Read, W: Write + ?Sized>(
+    reader: &mut csv::Reader<R>,
+    query: &Query,
+    settings: &StreamOptions,
+    out: &mut W,
+) -> Result<(), Error> {
+    let headers = reader.headers()?.clone();
+    let plan = query.plan(&headers)?;
+    if plan.is_sorted() {
+        return Err(Error::invalid_argument(
+            "Sorting needs the whole file in memory and can't be streamed",
+        ));
+    }
+
+    let mut out = BufWriter::new(out);
+    let mut rows = reader
+        .records()
+        .filter(|result| match result {
+            Ok(record) => plan.keep(record),
+            Err(_) => true,
+        })
+        .skip(query.skip)
+        .take(query.head.unwrap_or(usize::MAX))
+        .map(|result| result.map(|record| plan.project(&record)));
+    let headers = plan.project(&headers);
+
+    if let Some(tail) = query.tail {
+        let mut last: VecDeque<StringRecord> = VecDeque::with_capacity(tail.min(4096));
+        for row in rows {
+            if last.len() == tail {
+                last.pop_front();
+            }
+            if tail > 0 {
+                last.push_back(row?);
+            }
+        }
+        let last: Vec<StringRecord> = last.into();
+        let layout = Layout::new(&headers, &last, settings.max_width);
+        layout.write_header(&mut out, &headers)?;
+        for row in &last {
+            layout.write_row(&mut out, row)?;
+        }
+    } else {
+        let mut sample = Vec::new();
+        for row in rows.by_ref().take(settings.sample) {
+            sample.push(row?);
+        }
+        let layout = Layout::new(&headers, &sample, settings.max_width);
+        layout.write_header(&mut out, &headers)?;
+        for row in &sample {
+            layout.write_row(&mut out, row)?;
+        }
+        drop(sample);
+        for row in rows {
+            layout.write_row(&mut out, &row?)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(data: &str, options: &str) -> String {
+        let options = Options::from_spec(options).unwrap();
+        let mut out = Vec::new();
+        stream_table(
+            &mut csv::Reader::from_reader(data.as_bytes()),
+            &Query::from_options(&options).unwrap(),
+            &StreamOptions::from_options(&options).unwrap(),
+            &mut out,
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn widths_come_from_the_sample() {
+        let data = "name,age\nAl,30\nBo,4\nCyril the Great,100\n";
+        assert_eq!(
+            stream(data, "sample=2"),
+            "name | age\n-----+----\nAl   |  30\nBo   |   4\nCyr… | 100\n"
+        );
+    }
+
+    #[test]
+    fn skip_head_and_tail() {
+        let data = "n\n1\n2\n3\n4\n5\n";
+        assert_eq!(stream(data, "skip=1,head=2"), "n\n-\n2\n3\n");
+        assert_eq!(stream(data, "tail=2,where=n<5"), "n\n-\n3\n4\n");
+    }
+
+    #[test]
+    fn sorting_is_refused() {
+        let options = Options::from_spec("sort=n").unwrap();
+        let result = stream_table(
+            &mut csv::Reader::from_reader("n\n1\n".as_bytes()),
+            &Query::from_options(&options).unwrap(),
+            &StreamOptions::default(),
+            &mut Vec::new(),
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Encoding and decoding: base64, hex, percent-encoding, HTML entities and
+//! JSON strings.
+//!
+//! Decoders fail with [`Error::Transform`] on malformed input and when the
+//! decoded bytes are not UTF-8 text.
+
+use crate::error::Error;
+use crate::options::Options;
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+// Everything except the unreserved characters, like `encodeURIComponent`
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+// Like `encodeURI`, the characters with a meaning in a URL are kept
+const FULL_URL: &AsciiSet = &COMPONENT
+    .remove(b';')
+    .remove(b',')
+    .remove(b'/')
+    .remove(b'?')
+    .remove(b':')
+    .remove(b'@')
+    .remove(b'&')
+    .remove(b'=')
+    .remove(b'+')
+    .remove(b'$')
+    .remove(b'!')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'#');
+
+fn base64_engine(options: &Options) -> GeneralPurpose {
+    let alphabet = if options.flag("url-safe") {
+        &alphabet::URL_SAFE
+    } else {
+        &alphabet::STANDARD
+    };
+    let config = GeneralPurposeConfig::new()
+        .with_encode_padding(!options.flag("no-pad"))
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent);
+    GeneralPurpose::new(alphabet, config)
+}
+
+fn utf8(bytes: Vec<u8>, what: &str) -> Result<String, Error> {
+    String::from_utf8(bytes)
+        .map_err(|e| Error::transform(format!("The decoded {} is not UTF-8 text: {}", what, e)))
+}
+
+/// `hello?` --> `aGVsbG8/`, with the `url-safe` flag `aGVsbG8_`. The
+/// `no-pad` flag leaves out the trailing `=`.
+pub fn base64_encode(input: &str, options: &Options) -> Result<String, Error> {
+    Ok(base64_engine(options).encode(input))
+}
+
+/// `aGVsbG8/` --> `hello?`, the padding is optional.
+pub fn base64_decode(input: &str, options: &Options) -> Result<String, Error> {
+    let bytes = base64_engine(options)
+        .decode(input.trim())
+        .map_err(|e| Error::transform(format!("Invalid base64: {}", e)))?;
+    utf8(bytes, "base64")
+}
+
+/// `hi!` --> `686921`
+pub fn hex_encode(input: &str) -> Result<String, Error> {
+    Ok(hex::encode(input))
+}
+
+/// `68 69 21` --> `hi!`, whitespace between the bytes is ignored.
+pub fn hex_decode(input: &str) -> Result<String, Error> {
+    let digits: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = hex::decode(digits).map_err(|e| Error::transform(format!("Invalid hex: {}", e)))?;
+    utf8(bytes, "hex")
+}
+
+/// `a b&c/d` --> `a%20b%26c%2Fd`. With the `full` flag the input is a whole
+/// URL and the characters with a meaning in it are kept,
+/// `https://x.cz/a b?q=1` --> `https://x.cz/a%20b?q=1`.
+pub fn url_encode(input: &str, options: &Options) -> Result<String, Error> {
+    let set = if options.flag("full") {
+        FULL_URL
+    } else {
+        COMPONENT
+    };
+    Ok(utf8_percent_encode(input, set).to_string())
+}
+
+/// `a%20b%26c` --> `a b&c`. With the `plus` flag `+` is a space, as in
+/// submitted forms.
+pub fn url_decode(input: &str, options: &Options) -> Result<String, Error> {
+    let input = if options.flag("plus") {
+        input.replace('+', " ")
+    } else {
+        input.to_string()
+    };
+    utf8(percent_decode_str(&input).collect(), "URL")
+}
+
+/// `<a href="x">` --> `&lt;a href=&quot;x&quot;&gt;`
+pub fn html_escape(input: &str) -> Result<String, Error> {
+    Ok(html_escape::encode_quoted_attribute(input).into_owned())
+}
+
+/// `&lt;p&gt;&eacute;&#233;` --> `<p>éé`, named and numeric entities are
+/// decoded.
+pub fn html_unescape(input: &str) -> Result<String, Error> {
+    Ok(html_escape::decode_html_entities(input).into_owned())
+}
+
+/// `say "hi"\n` --> `say \"hi\"\\n`, the contents of a JSON string.
+pub fn json_escape(input: &str) -> Result<String, Error> {
+    let quoted = serde_json::to_string(input).map_err(|e| Error::transform(e.to_string()))?;
+    Ok(quoted[1..quoted.len() - 1].to_string())
+}
+
+/// `say \"hi\"!` --> `say "hi"!`. The input may be wrapped in the
+/// quotes of the JSON string.
+pub fn json_unescape(input: &str) -> Result<String, Error> {
+    let quoted = if input.len() >= 2 && input.starts_with('"') && input.ends_with('"') {
+        input.to_string()
+    } else {
+        format!("\"{}\"", input)
+    };
+    serde_json::from_str(&quoted)
+        .map_err(|e| Error::transform(format!("Invalid JSON string: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(spec: &str) -> Options {
+        Options::from_spec(spec).unwrap()
+    }
+
+    #[test]
+    fn base64_variants() {
+        let none = Options::new();
+        assert_eq!(base64_encode("hello?>", &none).unwrap(), "aGVsbG8/Pg==");
+        assert_eq!(
+            base64_encode("hello?>", &options("url-safe,no-pad")).unwrap(),
+            "aGVsbG8_Pg"
+        );
+        assert_eq!(base64_decode("aGVsbG8/Pg==", &none).unwrap(), "hello?>");
+        assert_eq!(base64_decode(" aGVsbG8/Pg\n", &none).unwrap(), "hello?>");
+        assert_eq!(
+            base64_decode("aGVsbG8_Pg", &options("url-safe")).unwrap(),
+            "hello?>"
+        );
+        assert_eq!(
+            base64_decode("aGVsbG8_Pg", &none).unwrap_err().kind(),
+            "transform"
+        );
+        assert!(base64_decode("/w==", &none).is_err());
+    }
+
+    #[test]
+    fn hex_bytes() {
+        assert_eq!(hex_encode("hi!").unwrap(), "686921");
+        assert_eq!(hex_decode("68 69 21").unwrap(), "hi!");
+        assert_eq!(hex_decode("c5be").unwrap(), "ž");
+        assert!(hex_decode("6").is_err());
+        assert!(hex_decode("zz").is_err());
+    }
+
+    #[test]
+    fn url_component_and_full() {
+        let none = Options::new();
+        assert_eq!(
+            url_encode("a b&c/d~ž", &none).unwrap(),
+            "a%20b%26c%2Fd~%C5%BE"
+        );
+        assert_eq!(
+            url_encode("https://x.cz/a b?q=1&r=ž#top", &options("full")).unwrap(),
+            "https://x.cz/a%20b?q=1&r=%C5%BE#top"
+        );
+        assert_eq!(url_decode("a%20b%26c+d", &none).unwrap(), "a b&c+d");
+        assert_eq!(url_decode("a+b", &options("plus")).unwrap(), "a b");
+        assert!(url_decode("%ff", &none).is_err());
+    }
+
+    #[test]
+    fn html_entities() {
+        assert_eq!(
+            html_escape("<a href=\"x\">'&'</a>").unwrap(),
+            "&lt;a href=&quot;x&quot;&gt;&#x27;&amp;&#x27;&lt;/a&gt;"
+        );
+        assert_eq!(
+            html_unescape("&lt;p&gt;&eacute;&#233;&#x27;").unwrap(),
+            "<p>éé'"
+        );
+    }
+
+    #[test]
+    fn json_strings() {
+        assert_eq!(
+            json_escape("say \"hi\"\n\tž").unwrap(),
+            "say \\\"hi\\\"\\n\\tž"
+        );
+        assert_eq!(
+            json_unescape("say \\\"hi\\\"\\u0021").unwrap(),
+            "say \"hi\"!"
+        );
+        assert_eq!(json_unescape("\"a\\nb\"").unwrap(), "a\nb");
+        assert_eq!(json_unescape("bad \\x").unwrap_err().kind(), "transform");
+    }
+}
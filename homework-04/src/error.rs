@@ -0,0 +1,297 @@
+//! Error type of the library.
+//!
+//! Every variant maps to its own process exit code, see [`Error::exit_code`],
+//! and has a stable machine readable name, see [`Error::kind`].
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The operation is not in the registry.
+    UnknownOperation {
+        name: String,
+        available: Vec<String>,
+    },
+    /// Wrong pipeline syntax or option value.
+    InvalidArgument(String),
+    /// The input was rejected by a validator.
+    Validation { validator: String, input: String },
+    /// The transform can't process the input.
+    Transform(String),
+    /// Reading or writing failed, `path` is the file when there is one.
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// The CSV file is malformed. `line` and `column` are 1-based.
+    Csv {
+        path: Option<PathBuf>,
+        line: Option<u64>,
+        column: Option<u64>,
+        source: csv::Error,
+    },
+    /// A stage of a pipeline failed, `index` is 1-based.
+    Stage {
+        index: usize,
+        operation: String,
+        source: Box<Error>,
+    },
+    /// A line of a script failed, `line` is 1-based.
+    Script {
+        path: PathBuf,
+        line: usize,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    pub fn transform(message: impl Into<String>) -> Error {
+        Error::Transform(message.into())
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Error {
+        Error::InvalidArgument(message.into())
+    }
+
+    /// I/O error of the file at `path`.
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Error {
+        Error::Io {
+            path: Some(path.into()),
+            source,
+        }
+    }
+
+    /// CSV error of the file at `path`, errors while opening the file are
+    /// turned into [`Error::Io`].
+    pub fn csv(path: Option<PathBuf>, source: csv::Error) -> Error {
+        let (line, column) = match source.kind() {
+            csv::ErrorKind::Io(_) => {
+                return match source.into_kind() {
+                    csv::ErrorKind::Io(source) => Error::Io { path, source },
+                    _ => unreachable!(),
+                }
+            }
+            csv::ErrorKind::Utf8 { pos, err } => (
+                pos.as_ref().map(|pos| pos.line()),
+                Some(err.field() as u64 + 1),
+            ),
+            csv::ErrorKind::UnequalLengths { pos, .. } => {
+                (pos.as_ref().map(|pos| pos.line()), None)
+            }
+            _ => (source.position().map(|pos| pos.line()), None),
+        };
+        Error::Csv {
+            path,
+            line,
+            column,
+            source,
+        }
+    }
+
+    /// Fills in the file of I/O and CSV errors which don't have one.
+    pub fn with_path(self, file: impl Into<PathBuf>) -> Error {
+        match self {
+            Error::Io { path: None, source } => Error::Io {
+                path: Some(file.into()),
+                source,
+            },
+            Error::Csv {
+                path: None,
+                line,
+                column,
+                source,
+            } => Error::Csv {
+                path: Some(file.into()),
+                line,
+                column,
+                source,
+            },
+            other => other,
+        }
+    }
+
+    /// The error which caused the failure, without the stages and script
+    /// lines around it.
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Stage { source, .. } | Error::Script { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    /// Stable name of the variant, e.g. `validation`.
+    pub fn kind(&self) -> &'static str {
+        match self.root() {
+            Error::UnknownOperation { .. } => "unknown_operation",
+            Error::InvalidArgument(_) => "invalid_argument",
+            Error::Validation { .. } => "validation",
+            Error::Transform(_) => "transform",
+            Error::Io { .. } => "io",
+            Error::Csv { .. } => "csv",
+            Error::Stage { .. } | Error::Script { .. } => unreachable!(),
+        }
+    }
+
+    /// Exit code of the process when it ends with this error.
+    ///
+    /// | code | error                          |
+    /// |------|--------------------------------|
+    /// | 1    | [`Error::Transform`]           |
+    /// | 2    | [`Error::InvalidArgument`]     |
+    /// | 3    | [`Error::UnknownOperation`]    |
+    /// | 4    | [`Error::Validation`]          |
+    /// | 5    | [`Error::Io`]                  |
+    /// | 6    | [`Error::Csv`]                 |
+    pub fn exit_code(&self) -> i32 {
+        match self.root() {
+            Error::Transform(_) => 1,
+            Error::InvalidArgument(_) => 2,
+            Error::UnknownOperation { .. } => 3,
+            Error::Validation { .. } => 4,
+            Error::Io { .. } => 5,
+            Error::Csv { .. } => 6,
+            Error::Stage { .. } | Error::Script { .. } => unreachable!(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownOperation { name, available } => write!(
+                f,
+                "Unknown modification: {}\nAvailable modifications: {}",
+                name,
+                available.join(", ")
+            ),
+            Error::InvalidArgument(message) | Error::Transform(message) => {
+                write!(f, "{}", message)
+            }
+            Error::Validation { validator, .. } => {
+                write!(f, "Invalid input for validator: {}", validator)
+            }
+            Error::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: {}", path.display(), source),
+            Error::Io { path: None, source } => write!(f, "{}", source),
+            Error::Csv {
+                path,
+                line,
+                column,
+                source,
+            } => {
+                write!(f, "Invalid CSV")?;
+                if let Some(path) = path {
+                    write!(f, " in {}", path.display())?;
+                }
+                if let Some(line) = line {
+                    write!(f, " at line {}", line)?;
+                }
+                if let Some(column) = column {
+                    write!(f, ", column {}", column)?;
+                }
+                write!(f, ": {}", source)
+            }
+            Error::Stage {
+                index,
+                operation,
+                source,
+            } => write!(f, "Stage {} ({}) failed: {}", index, operation, source),
+            Error::Script { path, line, source } => {
+                write!(f, "{}:{}: {}", path.display(), line, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Csv { source, .. } => Some(source),
+            Error::Stage { source, .. } | Error::Script { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Io { path: None, source }
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(source: csv::Error) -> Self {
+        Error::csv(None, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stage_errors_keep_the_cause() {
+        let error = Error::Stage {
+            index: 2,
+            operation: "csv".to_string(),
+            source: Box::new(Error::io("data.csv", io::ErrorKind::NotFound.into())),
+        };
+        assert_eq!(error.kind(), "io");
+        assert_eq!(error.exit_code(), 5);
+        assert!(error
+            .to_string()
+            .starts_with("Stage 2 (csv) failed: data.csv: "));
+    }
+
+    #[test]
+    fn script_errors_name_the_line() {
+        let error = Error::Script {
+            path: "release.ops".into(),
+            line: 7,
+            source: Box::new(Error::Stage {
+                index: 1,
+                operation: "b64dec".to_string(),
+                source: Box::new(Error::transform("Invalid base64")),
+            }),
+        };
+        assert_eq!(error.kind(), "transform");
+        assert_eq!(error.exit_code(), 1);
+        assert_eq!(
+            error.to_string(),
+            "release.ops:7: Stage 1 (b64dec) failed: Invalid base64"
+        );
+    }
+
+    #[test]
+    fn csv_errors_have_line_and_column() {
+        let data = "a,b\n1,2\n3\n";
+        let mut reader = csv::Reader::from_reader(data.as_bytes());
+        let source = reader.records().find_map(|r| r.err()).unwrap();
+        match Error::csv(Some("data.csv".into()), source) {
+            Error::Csv { line, column, .. } => {
+                assert_eq!(line, Some(3));
+                assert_eq!(column, None);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let data = b"a,b\n1,2\n3,\xff\n";
+        let mut reader = csv::Reader::from_reader(&data[..]);
+        let source = reader.records().find_map(|r| r.err()).unwrap();
+        let error = Error::csv(None, source);
+        assert_eq!(error.exit_code(), 6);
+        assert!(matches!(
+            error,
+            Error::Csv {
+                line: Some(3),
+                column: Some(2),
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,177 @@
+//! Hashes and checksums of text or files.
+//!
+//! The input is hashed as UTF-8 text. With the `file` flag it is a path
+//! instead, like for the `csv` command, and the file is hashed while it is
+//! read. The digest is printed as lowercase hex, or as base64 with
+//! `encoding=base64`.
+
+use crate::error::Error;
+use crate::options::Options;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::Digest;
+use std::fs::File;
+use std::io::Read;
+
+/// Encodings accepted by the `encoding` option.
+pub const ENCODINGS: [&str; 2] = ["hex", "base64"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+    Crc32,
+}
+
+// Running state of one of the algorithms
+enum Hasher {
+    Md5(md5::Md5),
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Blake3(Box<blake3::Hasher>),
+    Crc32(crc32fast::Hasher),
+}
+
+impl Hasher {
+    fn new(algorithm: Algorithm) -> Hasher {
+        match algorithm {
+            Algorithm::Md5 => Hasher::Md5(md5::Md5::new()),
+            Algorithm::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            Algorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            Algorithm::Sha512 => Hasher::Sha512(sha2::Sha512::new()),
+            Algorithm::Blake3 => Hasher::Blake3(Box::default()),
+            Algorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Md5(hasher) => hasher.update(bytes),
+            Hasher::Sha1(hasher) => hasher.update(bytes),
+            Hasher::Sha256(hasher) => hasher.update(bytes),
+            Hasher::Sha512(hasher) => hasher.update(bytes),
+            Hasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+            Hasher::Crc32(hasher) => hasher.update(bytes),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finalize().to_vec(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+            // Big endian, the way CRC-32 checksums are usually written
+            Hasher::Crc32(hasher) => hasher.finalize().to_be_bytes().to_vec(),
+        }
+    }
+}
+
+impl Algorithm {
+    pub fn digest(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut hasher = Hasher::new(*self);
+        hasher.update(bytes);
+        hasher.finalize()
+    }
+
+    /// Digest of everything read from `reader`, read in chunks.
+    pub fn digest_reader(&self, reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
+        let mut hasher = Hasher::new(*self);
+        let mut buffer = [0; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(hasher.finalize());
+            }
+            hasher.update(&buffer[..read]);
+        }
+    }
+}
+
+/// Digest of the input, or of the file at the input path with the `file`
+/// flag, encoded as given by the `encoding` option.
+pub fn hash(algorithm: Algorithm, input: &str, options: &Options) -> Result<String, Error> {
+    let encoding = options.get("encoding").unwrap_or("hex");
+    if !ENCODINGS.contains(&encoding) {
+        return Err(Error::invalid_argument(format!(
+            "Unknown encoding: {}\nAvailable encodings: {}",
+            encoding,
+            ENCODINGS.join(", ")
+        )));
+    }
+
+    let digest = if options.flag("file") {
+        let path = input.trim();
+        let mut file = File::open(path).map_err(|e| Error::io(path, e))?;
+        algorithm
+            .digest_reader(&mut file)
+            .map_err(|e| Error::io(path, e))?
+    } else {
+        algorithm.digest(input.as_bytes())
+    };
+
+    Ok(match encoding {
+        "base64" => STANDARD.encode(digest),
+        _ => hex::encode(digest),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn hex_digest(algorithm: Algorithm, input: &str) -> String {
+        hash(algorithm, input, &Options::new()).unwrap()
+    }
+
+    #[test]
+    fn known_digests() {
+        assert_eq!(
+            hex_digest(Algorithm::Md5, "abc"),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            hex_digest(Algorithm::Sha1, "abc"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex_digest(Algorithm::Sha256, "abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(hex_digest(Algorithm::Sha512, "abc").starts_with("ddaf35a193617aba"));
+        assert_eq!(
+            hex_digest(Algorithm::Blake3, ""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(hex_digest(Algorithm::Crc32, "123456789"), "cbf43926");
+    }
+
+    #[test]
+    fn base64_and_files() {
+        let options = Options::from_spec("encoding=base64").unwrap();
+        assert_eq!(
+            hash(Algorithm::Md5, "abc", &options).unwrap(),
+            "kAFQmDzST7DWlj99KOF/cg=="
+        );
+        let options = Options::from_spec("encoding=base32").unwrap();
+        assert!(hash(Algorithm::Md5, "abc", &options).is_err());
+
+        let path = std::env::temp_dir().join("homework-04-hash-test.txt");
+        File::create(&path).unwrap().write_all(b"abc").unwrap();
+        let options = Options::from_spec("file").unwrap();
+        let digest = hash(Algorithm::Sha1, path.to_str().unwrap(), &options).unwrap();
+        assert_eq!(digest, hex_digest(Algorithm::Sha1, "abc"));
+        std::fs::remove_file(&path).unwrap();
+
+        let err = hash(Algorithm::Sha1, "/does/not/exist", &options).unwrap_err();
+        assert_eq!(err.kind(), "io");
+    }
+}
@@ -0,0 +1,31 @@
+// Reading the input from stdin when it is not given on the command line
+use homework_04::Error;
+
+type Validator = fn(&str) -> (bool, &str);
+
+pub fn is_valid_string(input: &str) -> (bool, &str) {
+    (!input.trim().is_empty(), "string_validation")
+}
+
+fn get_input(input_prompt: String) -> Result<String, Error> {
+    println!("{}\n", input_prompt);
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+pub fn get_valid_input(validators: Vec<Validator>, input_prompt: String) -> Result<String, Error> {
+    let input = get_input(input_prompt)?;
+
+    for validator in validators {
+        let (valid, validation_type) = validator(&input);
+
+        if !valid {
+            return Err(Error::Validation {
+                validator: validation_type.to_string(),
+                input,
+            });
+        }
+    }
+    Ok(input)
+}
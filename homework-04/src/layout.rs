@@ -0,0 +1,308 @@
+//! Layout of text in fixed-width output: word wrapping, justification,
+//! alignment and truncation.
+//!
+//! Widths are terminal columns, the same as in the CSV tables, so `東京` is
+//! four columns wide and a combining accent takes none. Every line of the
+//! input is laid out on its own and empty lines are kept.
+
+use crate::error::Error;
+use crate::options::Options;
+use crate::width::{display_width, pad_with, truncate_with, Align};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Width used without the `width` option.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// How [`wrap`] and [`justify`] break the lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrapOptions {
+    pub width: usize,
+    /// Words wider than the line are broken, otherwise they stick out.
+    pub break_long_words: bool,
+    /// Spaces in front of the continuation lines, a hanging indent.
+    pub indent: usize,
+}
+
+impl Default for WrapOptions {
+    fn default() -> Self {
+        WrapOptions {
+            width: DEFAULT_WIDTH,
+            break_long_words: false,
+            indent: 0,
+        }
+    }
+}
+
+impl WrapOptions {
+    /// Reads the `width`, `indent` and `break-long-words` options.
+    pub fn from_options(options: &Options) -> Result<WrapOptions, Error> {
+        let width = options.parse("width")?.unwrap_or(DEFAULT_WIDTH);
+        let indent = options.parse("indent")?.unwrap_or(0);
+        if indent >= width {
+            return Err(Error::invalid_argument(format!(
+                "The width {} leaves no room for the text after the indent {}",
+                width, indent
+            )));
+        }
+        Ok(WrapOptions {
+            width,
+            break_long_words: options.flag("break-long-words"),
+            indent,
+        })
+    }
+}
+
+/// Breaks `line` into lines of at most `width` columns between words.
+///
+/// The leading whitespace of the line is kept on every line it is broken
+/// into, so indented list items stay indented.
+pub fn wrap_line(line: &str, options: &WrapOptions) -> Vec<String> {
+    let content = line.trim_start();
+    if content.is_empty() {
+        return vec![String::new()];
+    }
+    let lead = &line[..line.len() - content.len()];
+    let continuation = format!("{}{}", lead, " ".repeat(options.indent));
+
+    let mut lines = Vec::new();
+    let mut current = lead.to_string();
+    let mut current_width = display_width(lead);
+    // Whether a word was already put on the current line
+    let mut has_words = false;
+    let mut new_line = |current: &mut String, current_width: &mut usize| {
+        lines.push(std::mem::replace(current, continuation.clone()));
+        *current_width = display_width(&continuation);
+    };
+
+    // A word wider than a whole line is broken where it starts
+    let line_width = options.width - display_width(&continuation).min(options.width);
+    for word in content.split_whitespace() {
+        let word_width = display_width(word);
+        let broken = options.break_long_words && word_width > line_width;
+        if has_words && !broken && current_width + 1 + word_width > options.width {
+            new_line(&mut current, &mut current_width);
+            has_words = false;
+        }
+        let mut separator = if has_words { " " } else { "" };
+        if broken {
+            for grapheme in word.graphemes(true) {
+                let grapheme_width = display_width(grapheme);
+                // At least one grapheme on every line, however narrow it is
+                if has_words && current_width + separator.len() + grapheme_width > options.width {
+                    new_line(&mut current, &mut current_width);
+                    separator = "";
+                }
+                current.push_str(separator);
+                current.push_str(grapheme);
+                current_width += separator.len() + grapheme_width;
+                separator = "";
+                has_words = true;
+            }
+        } else {
+            current.push_str(separator);
+            current.push_str(word);
+            current_width += separator.len() + word_width;
+            has_words = true;
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+/// `The quick brown fox` with `width=10` -->
+///
+/// ```text
+/// The quick
+/// brown fox
+/// ```
+pub fn wrap(input: &str, options: &Options) -> Result<String, Error> {
+    let options = WrapOptions::from_options(options)?;
+    Ok(input
+        .lines()
+        .flat_map(|line| wrap_line(line, &options))
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+/// Wraps the input and widens the spaces between the words so that every
+/// line ends at the width. The last line of a paragraph is left as it is.
+///
+/// `The quick brown fox jumps` with `width=12` -->
+///
+/// ```text
+/// The    quick
+/// brown    fox
+/// jumps
+/// ```
+pub fn justify(input: &str, options: &Options) -> Result<String, Error> {
+    let options = WrapOptions::from_options(options)?;
+    let mut justified = Vec::new();
+    for line in input.lines() {
+        let wrapped = wrap_line(line, &options);
+        let last = wrapped.len() - 1;
+        for (i, line) in wrapped.into_iter().enumerate() {
+            if i == last {
+                justified.push(line);
+            } else {
+                justified.push(justify_line(&line, options.width));
+            }
+        }
+    }
+    Ok(justified.join("\n"))
+}
+
+// Spreads the missing columns over the gaps between the words, the first
+// gaps get the extra spaces
+fn justify_line(line: &str, width: usize) -> String {
+    let content = line.trim_start();
+    let lead = &line[..line.len() - content.len()];
+    let words: Vec<&str> = content.split(' ').collect();
+    let gaps = words.len() - 1;
+    if gaps == 0 {
+        return line.to_string();
+    }
+    let missing = width.saturating_sub(display_width(line));
+    let mut justified = lead.to_string();
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            let extra = missing / gaps + usize::from(i <= missing % gaps);
+            justified.push_str(&" ".repeat(1 + extra));
+        }
+        justified.push_str(word);
+    }
+    justified
+}
+
+/// Pads every line to the `width` with the `fill` character, a space by
+/// default.
+///
+/// `Align::Right` is the `pad-left` operation, `42` --> `   42`, and
+/// `Align::Center` centers the line without its surrounding whitespace.
+pub fn align(input: &str, options: &Options, align: Align) -> Result<String, Error> {
+    let width = options.parse("width")?.unwrap_or(DEFAULT_WIDTH);
+    let fill = match options.get("fill") {
+        None => ' ',
+        Some(fill) => {
+            let mut chars = fill.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if display_width(fill) == 1 => c,
+                _ => {
+                    return Err(Error::invalid_argument(format!(
+                        "The fill '{}' must be a single character one column wide",
+                        fill
+                    )))
+                }
+            }
+        }
+    };
+    Ok(input
+        .lines()
+        .map(|line| match align {
+            Align::Center => pad_with(line.trim(), width, align, fill),
+            _ => pad_with(line, width, align, fill),
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+/// Cuts every line to the `width`, ending it with the `ellipsis` when it
+/// was cut. Grapheme clusters are kept whole.
+///
+/// `Hello World` with `width=8` --> `Hello W…`
+pub fn truncate(input: &str, options: &Options) -> Result<String, Error> {
+    let width = options.parse("width")?.unwrap_or(DEFAULT_WIDTH);
+    let ellipsis = options.get("ellipsis").unwrap_or("…");
+    Ok(input
+        .lines()
+        .map(|line| truncate_with(line, width, ellipsis))
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(spec: &str) -> Options {
+        Options::from_spec(spec).unwrap()
+    }
+
+    #[test]
+    fn wraps_on_word_boundaries() {
+        let text = "The quick brown fox jumps over the lazy dog";
+        assert_eq!(
+            wrap(text, &options("width=15")).unwrap(),
+            "The quick brown\nfox jumps over\nthe lazy dog"
+        );
+        assert_eq!(
+            wrap("東京 is a city\n\nin Japan", &options("width=8")).unwrap(),
+            "東京 is\na city\n\nin Japan"
+        );
+        assert_eq!(
+            wrap("  - first item of the list", &options("width=14,indent=2")).unwrap(),
+            "  - first item\n    of the\n    list"
+        );
+        assert!(wrap(text, &options("width=4,indent=4")).is_err());
+    }
+
+    #[test]
+    fn long_words() {
+        let text = "see https://example.com/a/long/path";
+        assert_eq!(
+            wrap(text, &options("width=10")).unwrap(),
+            "see\nhttps://example.com/a/long/path"
+        );
+        assert_eq!(
+            wrap(text, &options("width=10,break-long-words")).unwrap(),
+            "see https:\n//example.\ncom/a/long\n/path"
+        );
+        assert_eq!(
+            wrap(
+                "cafe\u{301}cafe\u{301}",
+                &options("width=3,break-long-words")
+            )
+            .unwrap(),
+            "caf\ne\u{301}ca\nfe\u{301}"
+        );
+    }
+
+    #[test]
+    fn justifies_all_but_the_last_line() {
+        assert_eq!(
+            justify("The quick brown fox jumps", &options("width=12")).unwrap(),
+            "The    quick\nbrown    fox\njumps"
+        );
+        assert_eq!(
+            justify("a b c d e f", &options("width=8")).unwrap(),
+            "a  b c d\ne f"
+        );
+        assert_eq!(justify_line("  a b c", 9), "  a  b  c");
+    }
+
+    #[test]
+    fn aligns_and_truncates_lines() {
+        assert_eq!(
+            align("42\n東京", &options("width=5"), Align::Right).unwrap(),
+            "   42\n 東京"
+        );
+        assert_eq!(
+            align("ab", &options("width=6,fill=."), Align::Left).unwrap(),
+            "ab...."
+        );
+        assert_eq!(
+            align("  title ", &options("width=9,fill=-"), Align::Center).unwrap(),
+            "--title--"
+        );
+        assert!(align("x", &options("fill=ab"), Align::Left).is_err());
+        assert!(align("x", &options("fill=東"), Align::Left).is_err());
+
+        assert_eq!(
+            truncate("Hello World\nHi", &options("width=8")).unwrap(),
+            "Hello W…\nHi"
+        );
+        assert_eq!(
+            truncate("Hello World", &options("width=8,ellipsis=...")).unwrap(),
+            "Hello..."
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Length of text in the units which matter for field limits.
+//!
+//! `é` written as `e` and a combining accent is 3 bytes, 2 code points,
+//! 1 grapheme and 1 column wide, a family emoji is 18 bytes, 5 code points,
+//! 1 grapheme and 2 columns.
+
+use crate::width::display_width;
+use std::fmt;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Units accepted by [`Lengths::get`].
+pub const UNITS: [&str; 4] = ["bytes", "code-points", "graphemes", "width"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lengths {
+    pub bytes: usize,
+    pub code_points: usize,
+    pub graphemes: usize,
+    pub width: usize,
+}
+
+impl Lengths {
+    pub fn of(s: &str) -> Lengths {
+        Lengths {
+            bytes: s.len(),
+            code_points: s.chars().count(),
+            graphemes: s.graphemes(true).count(),
+            width: display_width(s),
+        }
+    }
+
+    /// Length in one of the [`UNITS`], `chars` is accepted for code points.
+    pub fn get(&self, unit: &str) -> Option<usize> {
+        match unit {
+            "bytes" => Some(self.bytes),
+            "code-points" | "chars" => Some(self.code_points),
+            "graphemes" => Some(self.graphemes),
+            "width" => Some(self.width),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Lengths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bytes: {}\ncode points: {}\ngraphemes: {}\nwidth: {}",
+            self.bytes, self.code_points, self.graphemes, self.width
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_every_unit() {
+        assert_eq!(
+            Lengths::of("e\u{301}"),
+            Lengths {
+                bytes: 3,
+                code_points: 2,
+                graphemes: 1,
+                width: 1
+            }
+        );
+        let family = Lengths::of("👨\u{200d}👩\u{200d}👧");
+        assert_eq!(family.get("bytes"), Some(18));
+        assert_eq!(family.get("chars"), Some(5));
+        assert_eq!(family.get("graphemes"), Some(1));
+        assert_eq!(family.get("width"), Some(2));
+        assert_eq!(family.get("lines"), None);
+    }
+}
@@ -0,0 +1,41 @@
+//! String transformations behind the `homework-04` command line tool.
+//!
+//! Every [`StringOperation`] is backed by a pure function in [`operations`]
+//! taking `&str` and returning `Result<String, Error>`, so the
+//! transforms can be called directly without going through stdin.
+//! Custom operations can be added through the [`Transform`] trait.
+//!
+//! ```
+//! use homework_04::operations::{slugify_input, Pipeline};
+//!
+//! assert_eq!(slugify_input("Hello World").unwrap(), "hello-world");
+//!
+//! let pipeline: Pipeline = "lowercase|no-spaces".parse().unwrap();
+//! assert_eq!(pipeline.run("Hello World").unwrap(), "helloworld");
+//! ```
+
+pub mod case;
+pub mod cleanup;
+pub mod csv_operations;
+pub mod encoding;
+pub mod error;
+pub mod hash;
+pub mod layout;
+pub mod length;
+pub mod lines;
+pub mod locale;
+pub mod operations;
+pub mod options;
+pub mod pattern;
+pub mod slugs;
+pub mod stats;
+pub mod transform;
+pub mod width;
+
+pub use error::Error;
+pub use operations::{get_modified_input, Pipeline, StringOperation};
+pub use options::Options;
+pub use transform::{Registry, Transform};
+
+/// Version of the library, follows the crate version.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
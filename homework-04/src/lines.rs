@@ -0,0 +1,386 @@
+//! Operations on the lines of the input: sorting, removing repeated lines,
+//! shuffling and numbering.
+//!
+//! The input is split like [`str::lines`], so `\r\n` endings work and a
+//! final newline doesn't add an empty line. The result is joined with `\n`
+//! and ends with a newline when the input did.
+//! In batch mode these operations get the whole file at once, see
+//! [`Transform::whole_input`](crate::transform::Transform::whole_input).
+
+use crate::error::Error;
+use crate::locale::Locale;
+use crate::options::Options;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Orders accepted by the `order` option of [`sort_lines`].
+pub const ORDERS: [&str; 4] = ["lexical", "natural", "numeric", "locale"];
+
+/// Sorts the lines in the `order` given by the option:
+///
+/// - `lexical`, by code points, the default
+/// - `natural`, numbers inside the lines compare by value, `a2` before `a10`
+/// - `numeric`, by the number the line starts with, like `sort -g`
+/// - `locale`, ignoring case and accents first, as in a dictionary. With
+///   the `locale` option the letters of the language sort in their place,
+///   e.g. `č` after `c` and `ch` after `h` in Czech.
+///
+/// The `reverse` flag sorts in descending order.
+pub fn sort_lines(input: &str, options: &Options) -> Result<String, Error> {
+    let mut lines: Vec<&str> = input.lines().collect();
+    match options.get("order").unwrap_or("lexical") {
+        "lexical" => lines.sort(),
+        "natural" => lines.sort_by(|a, b| natural_cmp(a, b)),
+        "numeric" => lines.sort_by(|a, b| numeric_cmp(a, b)),
+        "locale" => {
+            let locale = Locale::from_options(options)?;
+            lines.sort_by_cached_key(|line| collation_key(line, &locale));
+        }
+        order => {
+            return Err(Error::invalid_argument(format!(
+                "Unknown order: {}\nAvailable orders: {}",
+                order,
+                ORDERS.join(", ")
+            )))
+        }
+    }
+    if options.flag("reverse") {
+        lines.reverse();
+    }
+    Ok(join(&lines, input))
+}
+
+// Joins the result, with the final newline of the input
+fn join<S: AsRef<str>>(lines: &[S], input: &str) -> String {
+    let mut joined = lines
+        .iter()
+        .map(AsRef::as_ref)
+        .collect::<Vec<&str>>()
+        .join("\n");
+    if !lines.is_empty() && input.ends_with('\n') {
+        joined.push('\n');
+    }
+    joined
+}
+
+// Splits into runs of ASCII digits and runs of everything else
+fn chunks(s: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for (i, c) in s.char_indices().skip(1) {
+        let previous = s[..i]
+            .chars()
+            .next_back()
+            .is_some_and(|p| p.is_ascii_digit());
+        if previous != c.is_ascii_digit() {
+            chunks.push(&s[start..i]);
+            start = i;
+        }
+    }
+    if start < s.len() {
+        chunks.push(&s[start..]);
+    }
+    chunks
+}
+
+// `file2` < `file10`, equal numbers with different zeros fall back to the
+// code points
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let is_number = |chunk: &str| chunk.starts_with(|c: char| c.is_ascii_digit());
+    for (x, y) in chunks(a).into_iter().zip(chunks(b)) {
+        let ordering = if is_number(x) && is_number(y) {
+            let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+            x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+        } else {
+            x.cmp(y)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    chunks(a).len().cmp(&chunks(b).len()).then_with(|| a.cmp(b))
+}
+
+// The number at the start of the line, `-1.5e3 ms` --> -1500
+fn leading_number(line: &str) -> Option<f64> {
+    let line = line.trim_start();
+    let end = line
+        .char_indices()
+        .take_while(|&(i, c)| {
+            c.is_ascii_digit() || c == '.' || (i == 0 && (c == '-' || c == '+')) || c == 'e'
+        })
+        .count();
+    // The longest prefix which is a number, `3e` is 3
+    (1..=end).rev().find_map(|end| line[..end].parse().ok())
+}
+
+// Lines without a number first, then by the value
+fn numeric_cmp(a: &str, b: &str) -> Ordering {
+    match (leading_number(a), leading_number(b)) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        (x, y) => x.is_some().cmp(&y.is_some()),
+    }
+    .then_with(|| a.cmp(b))
+}
+
+// Letters with their own place in the alphabet of a language, the weight is
+// added to the weight of the letter they follow
+fn tailoring(language: &str) -> &'static [(&'static str, char, u32)] {
+    match language {
+        "cs" => &[
+            ("ch", 'h', 1),
+            ("č", 'c', 1),
+            ("ř", 'r', 1),
+            ("š", 's', 1),
+            ("ž", 'z', 1),
+        ],
+        "da" | "nb" | "nn" | "no" => &[("æ", 'z', 1), ("ø", 'z', 2), ("å", 'z', 3)],
+        "es" => &[("ñ", 'n', 1)],
+        "fi" | "sv" => &[("å", 'z', 1), ("ä", 'z', 2), ("ö", 'z', 3)],
+        "pl" => &[
+            ("ą", 'a', 1),
+            ("ć", 'c', 1),
+            ("ę", 'e', 1),
+            ("ł", 'l', 1),
+            ("ń", 'n', 1),
+            ("ó", 'o', 1),
+            ("ś", 's', 1),
+            ("ź", 'z', 1),
+            ("ż", 'z', 2),
+        ],
+        _ => &[],
+    }
+}
+
+// Sort key in three levels: the letters without case and accents, then the
+// accents and then the case
+fn collation_key(line: &str, locale: &Locale) -> (Vec<u32>, String, String) {
+    let lowercase = locale.to_lowercase(&line.nfc().collect::<String>());
+    let tailoring = tailoring(locale.language());
+    // Leaves room for the tailored letters after every letter
+    let weight = |c: char| c as u32 * 4;
+
+    let mut letters = Vec::new();
+    let mut rest = lowercase.as_str();
+    while let Some(c) = rest.chars().next() {
+        if let Some((letter, after, offset)) = tailoring
+            .iter()
+            .find(|(letter, _, _)| rest.starts_with(letter))
+        {
+            letters.push(weight(*after) + offset);
+            rest = &rest[letter.len()..];
+            continue;
+        }
+        letters.extend(c.nfd().filter(|&c| !is_combining_mark(c)).map(weight));
+        rest = &rest[c.len_utf8()..];
+    }
+    (letters, lowercase.nfd().collect(), line.to_string())
+}
+
+// Lines compared by `uniq` and `dedupe`
+fn line_key(line: &str, ignore_case: bool) -> String {
+    if ignore_case {
+        line.to_lowercase()
+    } else {
+        line.to_string()
+    }
+}
+
+/// Collapses runs of equal lines into one, like the `uniq` command. Lines
+/// which repeat further apart are kept, see [`dedupe`] for those.
+///
+/// The `count` flag puts the number of the lines in front of them, the
+/// `ignore-case` flag compares the lines without case and keeps the first.
+pub fn uniq(input: &str, options: &Options) -> Result<String, Error> {
+    let ignore_case = options.flag("ignore-case");
+    let mut runs: Vec<(&str, String, usize)> = Vec::new();
+    for line in input.lines() {
+        let key = line_key(line, ignore_case);
+        match runs.last_mut() {
+            Some((_, last, count)) if *last == key => *count += 1,
+            _ => runs.push((line, key, 1)),
+        }
+    }
+    let count = options.flag("count");
+    let lines: Vec<String> = runs
+        .iter()
+        .map(|(line, _, n)| {
+            if count {
+                format!("{:>7} {}", n, line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    Ok(join(&lines, input))
+}
+
+/// Removes the lines which were already seen, keeping the first one and the
+/// order. The `ignore-case` flag compares the lines without case.
+///
+/// `b a b c a` --> `b a c`, one item per line
+pub fn dedupe(input: &str, options: &Options) -> Result<String, Error> {
+    let ignore_case = options.flag("ignore-case");
+    let mut seen = HashSet::new();
+    let lines: Vec<&str> = input
+        .lines()
+        .filter(|line| seen.insert(line_key(line, ignore_case)))
+        .collect();
+    Ok(join(&lines, input))
+}
+
+// SplitMix64, small and good enough for shuffling
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Puts the lines in random order. The same `seed` gives the same order, so
+/// a shuffle can be repeated.
+pub fn shuffle(input: &str, options: &Options) -> Result<String, Error> {
+    let seed = match options.parse::<u64>("seed")? {
+        Some(seed) => seed,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64),
+    };
+    let mut random = Random(seed);
+    let mut lines: Vec<&str> = input.lines().collect();
+    // Fisher–Yates
+    for i in (1..lines.len()).rev() {
+        let j = (random.next() % (i as u64 + 1)) as usize;
+        lines.swap(i, j);
+    }
+    Ok(join(&lines, input))
+}
+
+/// Puts the line number in front of every line, aligned to the widest one.
+/// The numbers begin at `start`, 1 by default, and are followed by the
+/// `delimiter`, a space by default. It is not called `separator` so that
+/// `slugify --separator _` in the same pipeline leaves it alone.
+///
+/// `a b` --> `1 a`, `2 b`, one item per line
+pub fn number_lines(input: &str, options: &Options) -> Result<String, Error> {
+    let start = options.parse::<usize>("start")?.unwrap_or(1);
+    let delimiter = options.get("delimiter").unwrap_or(" ");
+    let lines: Vec<&str> = input.lines().collect();
+    let width = (start + lines.len().saturating_sub(1)).to_string().len();
+    let numbered: Vec<String> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| format!("{:>width$}{}{}", start + i, delimiter, line, width = width))
+        .collect();
+    Ok(join(&numbered, input))
+}
+
+/// The last line first, like the `tac` command.
+pub fn reverse_lines(input: &str) -> Result<String, Error> {
+    let lines: Vec<&str> = input.lines().rev().collect();
+    Ok(join(&lines, input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(spec: &str) -> Options {
+        Options::from_spec(spec).unwrap()
+    }
+
+    fn sorted(input: &[&str], spec: &str) -> Vec<String> {
+        sort_lines(&input.join("\n"), &options(spec))
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn sort_orders() {
+        let files = ["file10", "file2", "File1", "file02"];
+        assert_eq!(sorted(&files, ""), ["File1", "file02", "file10", "file2"]);
+        assert_eq!(
+            sorted(&files, "order=natural"),
+            ["File1", "file02", "file2", "file10"]
+        );
+        assert_eq!(
+            sorted(&files, "order=natural,reverse"),
+            ["file10", "file2", "file02", "File1"]
+        );
+        assert_eq!(
+            sorted(&["10 ms", "-2.5", "n/a", "3e2 ms", "9"], "order=numeric"),
+            ["n/a", "-2.5", "9", "10 ms", "3e2 ms"]
+        );
+        assert!(sort_lines("a", &options("order=random")).is_err());
+    }
+
+    #[test]
+    fn locale_collation() {
+        let words = [
+            "zebra", "Čaj", "chata", "cukr", "hrad", "Éclair", "eda", "ihned",
+        ];
+        assert_eq!(
+            sorted(&words, "order=locale"),
+            ["Čaj", "chata", "cukr", "Éclair", "eda", "hrad", "ihned", "zebra"]
+        );
+        assert_eq!(
+            sorted(&words, "order=locale,locale=cs"),
+            ["cukr", "Čaj", "Éclair", "eda", "hrad", "chata", "ihned", "zebra"]
+        );
+        assert_eq!(
+            sorted(&["Öl", "Zug", "Apfel"], "order=locale,locale=sv"),
+            ["Apfel", "Zug", "Öl"]
+        );
+        assert_eq!(sorted(&["b", "B", "a"], "order=locale"), ["a", "B", "b"]);
+    }
+
+    #[test]
+    fn repeated_lines() {
+        let input = "a\na\nA\nb\na\n";
+        assert_eq!(uniq(input, &Options::new()).unwrap(), "a\nA\nb\na\n");
+        assert_eq!(
+            uniq(input, &options("count,ignore-case")).unwrap(),
+            "      3 a\n      1 b\n      1 a\n"
+        );
+        assert_eq!(dedupe(input, &Options::new()).unwrap(), "a\nA\nb\n");
+        assert_eq!(dedupe("b\na\nb", &options("ignore-case")).unwrap(), "b\na");
+    }
+
+    #[test]
+    fn shuffle_with_a_seed() {
+        let input: Vec<String> = (1..=20).map(|n| n.to_string()).collect();
+        let input = input.join("\n");
+        let shuffled = shuffle(&input, &options("seed=42")).unwrap();
+        assert_eq!(shuffled, shuffle(&input, &options("seed=42")).unwrap());
+        assert_ne!(shuffled, shuffle(&input, &options("seed=7")).unwrap());
+        assert_ne!(shuffled, input);
+        let mut lines: Vec<&str> = shuffled.lines().collect();
+        lines.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(lines.join("\n"), input);
+    }
+
+    #[test]
+    fn numbers_and_reverses_lines() {
+        let input = "a\nb\n\nd\r\n";
+        assert_eq!(
+            number_lines(input, &options("start=9,delimiter=': '")).unwrap(),
+            " 9: a\n10: b\n11: \n12: d\n"
+        );
+        assert_eq!(
+            number_lines("a\nb", &options("separator=_")).unwrap(),
+            "1 a\n2 b"
+        );
+        assert_eq!(reverse_lines(input).unwrap(), "d\n\nb\na\n");
+        assert_eq!(number_lines("", &Options::new()).unwrap(), "");
+    }
+}
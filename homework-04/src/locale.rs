@@ -0,0 +1,256 @@
+//! Language specific case mapping, selected with the `locale` option.
+//!
+//! The standard library maps case the same way for every language. That is
+//! wrong for a few of them:
+//!
+//! - Turkish and Azeri have a dotted `İ i` and a dotless `I ı`.
+//! - Lithuanian keeps the dot of `i` and `j` when an accent is put above it,
+//!   so `Ì` lowercases to `i̇̀`.
+//! - Greek drops the accents in uppercase, `Άλφα` --> `ΑΛΦΑ`.
+//!
+//! The locale also gives the small words which
+//! [`title_case_in`](crate::operations::title_case_in) keeps in lowercase,
+//! e.g. `of` and `and` in English.
+
+use crate::error::Error;
+use crate::options::Options;
+use std::str::FromStr;
+
+const COMBINING_DOT_ABOVE: char = '\u{307}';
+
+/// Language of the text, parsed from a tag like `tr`, `tr-TR` or `cs_CZ`.
+///
+/// Only the language matters, the region is ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locale {
+    language: String,
+}
+
+impl Locale {
+    /// Case mapping of the standard library and no small words.
+    pub fn root() -> Locale {
+        Locale {
+            language: String::new(),
+        }
+    }
+
+    /// The locale given by the `locale` option, [`Locale::root`] without it.
+    pub fn from_options(options: &Options) -> Result<Locale, Error> {
+        match options.get("locale") {
+            Some(tag) => tag.parse(),
+            None => Ok(Locale::root()),
+        }
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    fn is_turkic(&self) -> bool {
+        matches!(self.language.as_str(), "tr" | "az")
+    }
+
+    pub fn to_lowercase(&self, s: &str) -> String {
+        match self.language.as_str() {
+            "tr" | "az" => {
+                let mut lowercase = String::with_capacity(s.len());
+                let mut chars = s.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match c {
+                        // `I` with a combining dot is the dotted i
+                        'I' if chars.peek() == Some(&COMBINING_DOT_ABOVE) => {
+                            chars.next();
+                            lowercase.push('i');
+                        }
+                        'I' => lowercase.push('ı'),
+                        'İ' => lowercase.push('i'),
+                        _ => lowercase.push(c),
+                    }
+                }
+                // The rest, e.g. the final sigma, is left to the standard library
+                lowercase.to_lowercase()
+            }
+            "lt" => {
+                let mut lowercase = String::with_capacity(s.len());
+                let mut chars = s.chars().peekable();
+                while let Some(c) = chars.next() {
+                    match c {
+                        'Ì' => lowercase.push_str("i\u{307}\u{300}"),
+                        'Í' => lowercase.push_str("i\u{307}\u{301}"),
+                        'Ĩ' => lowercase.push_str("i\u{307}\u{303}"),
+                        'I' | 'J' | 'Į' if chars.peek().is_some_and(|&c| is_accent_above(c)) => {
+                            lowercase.extend(c.to_lowercase());
+                            lowercase.push(COMBINING_DOT_ABOVE);
+                        }
+                        _ => lowercase.push(c),
+                    }
+                }
+                lowercase.to_lowercase()
+            }
+            _ => s.to_lowercase(),
+        }
+    }
+
+    pub fn to_uppercase(&self, s: &str) -> String {
+        match self.language.as_str() {
+            "tr" | "az" => s
+                .chars()
+                .map(|c| match c {
+                    'i' => "İ".to_string(),
+                    'ı' => "I".to_string(),
+                    _ => c.to_uppercase().collect(),
+                })
+                .collect(),
+            "lt" => {
+                // The dot of a soft dotted letter is part of the uppercase letter
+                let mut uppercase = String::with_capacity(s.len());
+                let mut previous = None;
+                for c in s.chars() {
+                    let soft_dotted = matches!(previous, Some('i' | 'j' | 'į'));
+                    if !(c == COMBINING_DOT_ABOVE && soft_dotted) {
+                        uppercase.extend(c.to_uppercase());
+                    }
+                    if !is_accent_above(c) {
+                        previous = Some(c);
+                    }
+                }
+                uppercase
+            }
+            "el" => s
+                .to_uppercase()
+                .chars()
+                .filter_map(|c| match c {
+                    // Tonos and perispomeni, the diaeresis stays
+                    '\u{301}' | '\u{342}' | '\u{344}' => None,
+                    'Ά' => Some('Α'),
+                    'Έ' => Some('Ε'),
+                    'Ή' => Some('Η'),
+                    'Ί' => Some('Ι'),
+                    'Ό' => Some('Ο'),
+                    'Ύ' => Some('Υ'),
+                    'Ώ' => Some('Ω'),
+                    _ => Some(c),
+                })
+                .collect(),
+            _ => s.to_uppercase(),
+        }
+    }
+
+    /// First letter in uppercase, the rest in lowercase. Greek keeps the
+    /// accent on a capital letter.
+    pub fn capitalize(&self, word: &str) -> String {
+        let mut chars = word.chars();
+        let first_char = match chars.next() {
+            Some(c) => c,
+            None => return String::new(),
+        };
+        let first = match first_char {
+            'i' if self.is_turkic() => "İ".to_string(),
+            'ı' if self.is_turkic() => "I".to_string(),
+            _ => first_char.to_uppercase().collect(),
+        };
+        first + &self.to_lowercase(chars.as_str())
+    }
+
+    /// Articles, conjunctions and short prepositions which title case keeps
+    /// in lowercase unless they are the first or the last word.
+    pub fn small_words(&self) -> &'static [&'static str] {
+        match self.language.as_str() {
+            "en" => &[
+                "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor",
+                "of", "on", "or", "over", "per", "so", "the", "to", "up", "via", "vs", "with",
+                "yet",
+            ],
+            "cs" => &[
+                "a", "ale", "do", "i", "k", "ke", "na", "nad", "nebo", "o", "od", "po", "pod",
+                "pro", "před", "při", "s", "se", "u", "v", "ve", "z", "za", "ze",
+            ],
+            _ => &[],
+        }
+    }
+
+    pub fn is_small_word(&self, word: &str) -> bool {
+        let word = self.to_lowercase(word);
+        self.small_words().contains(&word.as_str())
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::root()
+    }
+}
+
+impl FromStr for Locale {
+    type Err = Error;
+
+    fn from_str(tag: &str) -> Result<Self, Self::Err> {
+        let language = tag.split(['-', '_']).next().unwrap_or("");
+        if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return Err(Error::invalid_argument(format!(
+                "Invalid locale: {}, expected a language like 'en' or 'tr-TR'",
+                tag
+            )));
+        }
+        Ok(Locale {
+            language: language.to_ascii_lowercase(),
+        })
+    }
+}
+
+// Combining marks written above the letter, these make Lithuanian keep the dot
+fn is_accent_above(c: char) -> bool {
+    matches!(c, '\u{300}'..='\u{314}' | '\u{33d}'..='\u{344}' | '\u{346}' | '\u{34a}'..='\u{34c}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(tag: &str) -> Locale {
+        tag.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_language_tags() {
+        assert_eq!(locale("tr-TR").language(), "tr");
+        assert_eq!(locale("CS_cz").language(), "cs");
+        assert!("t".parse::<Locale>().is_err());
+        assert!("česky".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn turkish_dotted_and_dotless_i() {
+        let tr = locale("tr");
+        assert_eq!(tr.to_uppercase("istanbul ılık"), "İSTANBUL ILIK");
+        assert_eq!(tr.to_lowercase("İSTANBUL ILIK"), "istanbul ılık");
+        assert_eq!(tr.to_lowercase("I\u{307}"), "i");
+        assert_eq!(tr.capitalize("izmir"), "İzmir");
+        assert_eq!(Locale::root().to_uppercase("istanbul"), "ISTANBUL");
+    }
+
+    #[test]
+    fn lithuanian_keeps_the_dot_under_accents() {
+        let lt = locale("lt");
+        assert_eq!(lt.to_lowercase("Ì"), "i\u{307}\u{300}");
+        assert_eq!(lt.to_lowercase("J\u{303}"), "j\u{307}\u{303}");
+        assert_eq!(lt.to_lowercase("IS"), "is");
+        assert_eq!(lt.to_uppercase("i\u{307}\u{300}"), "I\u{300}");
+    }
+
+    #[test]
+    fn greek_sigma_and_accents() {
+        let el = locale("el");
+        assert_eq!(el.to_lowercase("ΟΔΥΣΣΕΥΣ"), "οδυσσευς");
+        assert_eq!(el.to_uppercase("Άλφα ήταν"), "ΑΛΦΑ ΗΤΑΝ");
+        assert_eq!(el.capitalize("ΣΟΦΟΣ"), "Σοφος");
+    }
+
+    #[test]
+    fn small_words() {
+        assert!(locale("en-GB").is_small_word("Of"));
+        assert!(locale("cs").is_small_word("V"));
+        assert!(!Locale::root().is_small_word("of"));
+    }
+}
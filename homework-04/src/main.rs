@@ -1,70 +1,160 @@
-use crate::operations::operations::{get_modified_input, StringOperation, AVAILABLE_OPERATIONS};
+use crate::batch::run_batch;
+use crate::input::{get_valid_input, is_valid_string};
+use crate::repl::interactive;
+use crate::rpc::serve;
+use crate::script::run_script;
+use homework_04::{Error, Options, Pipeline, Registry};
 use std::env;
 use std::io;
-use std::thread;
-mod operations;
-use std::io::BufRead;
-use std::str::FromStr;
+mod batch;
+mod input;
+mod pool;
+mod repl;
+mod rpc;
+mod script;
+mod session;
+
+// Ask for the input of the first stage of the pipeline
+fn prompt_input(pipeline: &Pipeline) -> Result<String, Error> {
+    let hint = match pipeline.stages().first() {
+        Some(stage) => stage.transform.input_hint(),
+        None => "the string to modify",
+    };
+    get_valid_input(vec![is_valid_string], format!("Provide {}: ", hint))
+}
+
+fn print_help(registry: &Registry) {
+    println!(
+        "Usage: homework-04 [<command>[:<key>=<value>,...][|<command>...]] [--<key> <value>...]
+
+Without a command the interactive mode is started.
+Options after the command are given to every stage, e.g.
+\thomework-04 csv --select name,age --where 'age>30' --sort -age,name
+\thomework-04 csv --output markdown
+\thomework-04 csv --stream --head 1000 --max-width 30
+\thomework-04 title-case --locale en      lowercase, uppercase and title-case follow the language
+\thomework-04 slugify --separator _ --max-length 40 --stop-words a,the --translit '&=and'
+\thomework-04 sha256 --file --encoding base64    hash the file at the path instead of the text
+\thomework-04 stats --top 5 --stop-words a,the,of --output json
+\thomework-04 replace --pattern '(\\w+)@(\\w+)' --with '$2 at $1' [--first] [--ignore-case]
+\thomework-04 extract --pattern '(?<year>\\d{{4}})-(?<month>\\d{{2}})' [--group month]
+\thomework-04 matches --pattern '^#' [--invert] --in notes.txt
+\thomework-04 wrap --width 72 --indent 2 [--break-long-words]    also justify, center, pad-left, pad-right
+\thomework-04 truncate --width 30 --ellipsis ...
+\thomework-04 'sort-lines:order=natural|uniq:count' --in files.txt    also dedupe, shuffle --seed 1, nl, tac
+
+Batch mode, the command is applied to every line:
+\t--in <path>      input file, '-' for stdin, can be repeated
+\t--out <path>     output file instead of stdout
+\t--in-place       rewrite the input files
+\t--backup-suffix <suffix>  suffix of the backup made by --in-place, default .bak
+\t--unique         number repeated results, e.g. slugify --unique gives hello, hello-2
+\tsort-lines, uniq, dedupe, shuffle, number-lines and reverse-lines get the whole
+\tfile, they read stdin without --in
+
+Script mode, the lines of the file are run like in the interactive mode:
+\t--script <path>  file with one '<command> <input>' per line, '#' starts a comment,
+\t                 ':set name = value' sets $name and 'include <path>' runs another script
+\t--continue-on-error  run the rest of the script after a failed line
+
+RPC mode for editors and tools, JSON-RPC 2.0 with one message per line on stdin and stdout:
+\t--rpc            methods listOperations, transform {{operation, input, options}}
+\t                 and renderCsv {{path | data, format, options}}, --jobs requests run at once,
+\t                 a batch of requests is answered with an array of the responses
+
+Interactive mode options:
+\t--jobs <n>       number of worker threads
+\t--queue <n>      number of lines waiting for a worker before reading stops
+\t--unordered      print results as they finish, prefixed with the line number
+\t--history <path> history file, default ~/.homework-04_history
+\tOn a terminal Tab completes the commands, Ctrl+R searches the history and
+\t:help lists the REPL commands. A line ending with '\\' continues on the next one.
+
+Exit codes:
+\t1 transform failed, 2 invalid argument, 3 unknown command,
+\t4 invalid input, 5 I/O error, 6 invalid CSV
+
+Available commands:
+{}",
+        registry.help()
+    );
+}
+
+fn exit_with_error(error: Error) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(error.exit_code());
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let registry = Registry::default();
 
-    if args.len() < 2 {
-        println!(
-            "Entrering interactive mode...\n\tExpected format: <command> <input>\n\tAvailable commands: {}\nTo exit, press Ctrl+C\n",
-            AVAILABLE_OPERATIONS.join(", ")
-        );
-        let (tx, rx) = std::sync::mpsc::channel();
+    if args.len() > 1 && (args[1] == "--help" || args[1] == "-h") {
+        print_help(&registry);
+        return;
+    }
 
-        let input_thread = thread::spawn(move || {
-            let stdin = io::stdin();
-            for line in stdin.lock().lines() {
-                let line = line.unwrap();
-                // Handle the rest of the input as one string
-                // slugify Hello World --> hello-world
-                let mut parts = line.splitn(2, char::is_whitespace);
-                let command = parts.next().unwrap_or("");
-                let input = parts.next().unwrap_or("").to_string();
+    // Everything after the command, or all arguments without one, are options
+    let (command, option_args) = match args.get(1) {
+        Some(command) if !command.starts_with("--") => (Some(command), &args[2..]),
+        _ => (None, args.get(1..).unwrap_or_default()),
+    };
+    let options = Options::from_args(option_args).unwrap_or_else(|e| exit_with_error(e));
 
-                if !command.is_empty() {
-                    if input.trim().is_empty() {
-                        eprintln!("Input for command is missing. Expected: <command> <input>");
-                        continue;
-                    }
-                    let command = StringOperation::from_str(command);
-                    match command {
-                        Ok(cmd) => tx.send((cmd, input)).unwrap(),
-                        Err(err) => eprintln!("Erro: {}", err),
-                    }
-                } else {
-                    eprintln!("Invalid input format. Expected: <command> <input>");
+    match command {
+        None => {
+            if options.flag("rpc") {
+                let stdin = io::stdin().lock();
+                if let Err(e) = serve(registry, options, stdin, io::stdout()) {
+                    exit_with_error(e);
                 }
+                return;
             }
-        });
-
-        let processing_thread = thread::spawn(move || {
-            while let Ok((command, input)) = rx.recv() {
-                match get_modified_input(Some(&input), command) {
-                    Ok(modified_input) => {
-                        println!("{}", modified_input);
-                    }
-                    Err(e) => {
-                        eprintln!("{}", e);
+            if let Some(path) = options.get("script") {
+                match run_script(path, &registry, &options, &mut io::stdout().lock()) {
+                    Ok(report) if !report.failures.is_empty() => {
+                        for failure in &report.failures {
+                            eprintln!("{}", failure);
+                        }
+                        exit_with_error(Error::transform(format!(
+                            "{} of {} lines failed",
+                            report.failures.len(),
+                            report.lines
+                        )))
                     }
+                    Ok(_) => {}
+                    Err(e) => exit_with_error(e),
                 }
+                return;
             }
-        });
-
-        input_thread.join().unwrap();
-        processing_thread.join().unwrap();
-    } else {
-        match get_modified_input(None, StringOperation::from_str(&args[1]).unwrap()) {
-            Ok(modified_input) => {
-                println!("{}", modified_input);
+            if let Err(e) = interactive(registry, options) {
+                exit_with_error(e);
+            }
+        }
+        Some(command) => {
+            let pipeline = registry
+                .parse_pipeline(command)
+                .unwrap_or_else(|e| exit_with_error(e));
+            // Operations on all the lines, like sort-lines, read stdin without --in
+            let mut options = options;
+            if options.get("in").is_none() && pipeline.is_whole_input() {
+                options.set("in", "-");
+            }
+            if options.get("in").is_some() {
+                match run_batch(&pipeline, &options) {
+                    Ok(report) if report.failed > 0 => exit_with_error(Error::transform(format!(
+                        "{} of {} lines failed",
+                        report.failed, report.lines
+                    ))),
+                    Ok(_) => {}
+                    Err(e) => exit_with_error(e),
+                }
+                return;
             }
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
+            let result = prompt_input(&pipeline)
+                .and_then(|input| pipeline.write_with(&input, &options, &mut io::stdout().lock()));
+            if let Err(e) = result {
+                exit_with_error(e);
             }
         }
     }
@@ -1,283 +1,991 @@
-pub mod operations {
-    use slug::slugify;
-    use std::error::Error;
-
-    use std::str::FromStr;
-
-    pub const AVAILABLE_OPERATIONS: [&str; 7] = [
-        "lowercase",
-        "uppercase",
-        "no-spaces",
-        "slugify",
-        "reverse",
-        "title-case",
-        "csv",
-    ];
-
-    // Defined the enum with allowed values from string modifications
-    pub enum StringOperation {
-        Lowercase,
-        Uppercase,
-        NoSpaces,
-        Slugify,
-        Reverse,
-        TitleCase,
-        Csv,
-    }
-
-    impl FromStr for StringOperation {
-        // Read the string from arg and convert it to the enum
-        type Err = String;
-
-        fn from_str(s: &str) -> Result<Self, Self::Err> {
-            match s {
-                "uppercase" => Ok(StringOperation::Uppercase),
-                "lowercase" => Ok(StringOperation::Lowercase),
-                "reverse" => Ok(StringOperation::Reverse),
-                "no-spaces" => Ok(StringOperation::NoSpaces),
-                "slugify" => Ok(StringOperation::Slugify),
-                "title-case" => Ok(StringOperation::TitleCase),
-                "csv" => Ok(StringOperation::Csv),
-                _ => Err(format!(
-                    "Unknown modification: {s}\nAvailable modifications: {mods}",
-                    s = s,
-                    mods = AVAILABLE_OPERATIONS.join(", ")
-                )),
+//! Pure string transformations behind every [`StringOperation`].
+//!
+//! None of the functions here read from stdin, the caller is responsible
+//! for getting the input.
+
+use crate::case::{capitalize, words};
+use crate::cleanup;
+use crate::csv_operations::{self, CsvRecords};
+use crate::encoding;
+use crate::error::Error;
+use crate::hash::{self, Algorithm};
+use crate::layout;
+use crate::length::{Lengths, UNITS};
+use crate::lines;
+use crate::locale::Locale;
+use crate::options::{split_unquoted, Options};
+use crate::pattern;
+use crate::slugs::{slugify_with, SlugOptions};
+use crate::stats;
+use crate::transform::{Registry, Transform};
+use crate::width::Align;
+use csv::StringRecord;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::sync::Arc;
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::str::FromStr;
+
+// Every builtin operation is defined once in this table: variant, name used
+// on the command line, aliases and the description shown in the help text.
+macro_rules! builtin_operations {
+    ($($variant:ident => $name:literal, [$($alias:literal),*], $description:literal;)*) => {
+        /// Operations which can be applied to a string.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum StringOperation {
+            $($variant,)*
+        }
+
+        impl StringOperation {
+            /// All the builtin operations, in the order they are shown to the user.
+            pub const ALL: &'static [StringOperation] = &[$(StringOperation::$variant,)*];
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(StringOperation::$variant => $name,)*
+                }
+            }
+
+            pub fn aliases(&self) -> &'static [&'static str] {
+                match self {
+                    $(StringOperation::$variant => &[$($alias),*],)*
+                }
+            }
+
+            pub fn description(&self) -> &'static str {
+                match self {
+                    $(StringOperation::$variant => $description,)*
+                }
             }
         }
+    };
+}
+
+builtin_operations! {
+    Lowercase => "lowercase", ["lower"], "Convert to lowercase";
+    Uppercase => "uppercase", ["upper"], "Convert to uppercase";
+    NoSpaces => "no-spaces", ["nospaces"], "Remove all spaces, --all removes every kind of whitespace";
+    Slugify => "slugify", ["slug"], "Convert to a URL slug";
+    Reverse => "reverse", ["rev"], "Reverse the characters, keeping accents and emoji together";
+    TitleCase => "title-case", ["title"], "Capitalize the first letter of every word";
+    SnakeCase => "snake-case", ["snake"], "Convert an identifier to snake_case";
+    CamelCase => "camel-case", ["camel"], "Convert an identifier to camelCase";
+    PascalCase => "pascal-case", ["pascal"], "Convert an identifier to PascalCase";
+    KebabCase => "kebab-case", ["kebab"], "Convert an identifier to kebab-case";
+    ScreamingSnakeCase => "screaming-snake-case", ["screaming"], "Convert an identifier to SCREAMING_SNAKE_CASE";
+    TrainCase => "train-case", ["train"], "Convert an identifier to Train-Case";
+    Nfc => "nfc", [], "Unicode normalization form C, composed accents";
+    Nfd => "nfd", [], "Unicode normalization form D, decomposed accents";
+    Nfkc => "nfkc", [], "Unicode normalization form KC, compatibility characters replaced";
+    Nfkd => "nfkd", [], "Unicode normalization form KD";
+    AsciiFold => "ascii-fold", ["fold"], "Strip accents and transliterate to ASCII";
+    StripInvisible => "strip-invisible", [], "Remove zero width characters, soft hyphens and bidi controls";
+    NormalizeWhitespace => "normalize-whitespace", ["squeeze"], "Collapse all Unicode whitespace into single spaces, --keep-newlines";
+    AsciiQuotes => "ascii-quotes", ["smart-quotes"], "Replace typographic quotes and apostrophes with ASCII ones";
+    Length => "length", ["len"], "Count bytes, code points, graphemes and display width";
+    Graphemes => "graphemes", [], "List the grapheme clusters with their code points";
+    Wrap => "wrap", [], "Wrap the lines at --width columns, --indent for continuation lines, --break-long-words";
+    Justify => "justify", [], "Wrap the lines and stretch them to the full --width";
+    Center => "center", [], "Center every line in --width columns";
+    PadLeft => "pad-left", ["lpad"], "Pad every line on the left to --width columns, --fill sets the character";
+    PadRight => "pad-right", ["rpad"], "Pad every line on the right to --width columns";
+    Truncate => "truncate", ["trunc"], "Cut every line to --width columns, ending with --ellipsis";
+    SortLines => "sort-lines", ["sort"], "Sort the lines, --order lexical, natural, numeric or locale, --reverse";
+    Uniq => "uniq", [], "Collapse repeated adjacent lines, --count and --ignore-case";
+    Dedupe => "dedupe", [], "Remove repeated lines, keeping the first occurrence";
+    Shuffle => "shuffle", [], "Put the lines in random order, --seed repeats an order";
+    NumberLines => "number-lines", ["nl"], "Number the lines from --start, followed by --delimiter";
+    ReverseLines => "reverse-lines", ["tac"], "Reverse the order of the lines";
+    Stats => "stats", [], "Count characters, words and sentences, find frequent words and rate readability";
+    Replace => "replace", ["sub"], "Replace matches of the pattern, $1 in --with is a capture group";
+    Extract => "extract", [], "Print every match of the pattern, or its named groups";
+    Matches => "matches", ["grep"], "Keep only the input matching the pattern";
+    Base64Encode => "base64-encode", ["b64enc"], "Encode as base64, --url-safe and --no-pad select the variant";
+    Base64Decode => "base64-decode", ["b64dec"], "Decode base64, --url-safe for the URL-safe alphabet";
+    HexEncode => "hex-encode", ["hexenc"], "Encode the UTF-8 bytes as hex";
+    HexDecode => "hex-decode", ["hexdec"], "Decode hex bytes";
+    UrlEncode => "url-encode", ["urlenc"], "Percent-encode a URL component, --full for a whole URL";
+    UrlDecode => "url-decode", ["urldec"], "Decode percent-encoding, --plus turns + into spaces";
+    HtmlEscape => "html-escape", [], "Escape HTML special characters as entities";
+    HtmlUnescape => "html-unescape", [], "Decode named and numeric HTML entities";
+    JsonEscape => "json-escape", [], "Escape as the contents of a JSON string";
+    JsonUnescape => "json-unescape", [], "Decode the escapes of a JSON string";
+    Md5 => "md5", [], "MD5 hash, --file hashes the file at the path";
+    Sha1 => "sha1", [], "SHA-1 hash";
+    Sha256 => "sha256", [], "SHA-256 hash";
+    Sha512 => "sha512", [], "SHA-512 hash";
+    Blake3 => "blake3", [], "BLAKE3 hash";
+    Crc32 => "crc32", [], "CRC-32 checksum, --encoding base64 instead of hex";
+    Csv => "csv", [], "Print a CSV file as a table";
+}
+
+impl FromStr for StringOperation {
+    // Read the string from arg and convert it to the enum
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StringOperation::ALL
+            .iter()
+            .find(|op| op.name() == s || op.aliases().contains(&s))
+            .copied()
+            .ok_or_else(|| Error::UnknownOperation {
+                name: s.to_string(),
+                available: StringOperation::ALL
+                    .iter()
+                    .map(|op| op.name().to_string())
+                    .collect(),
+            })
     }
+}
 
-    fn is_valid_string(input: &str) -> (bool, &str) {
-        return (!input.trim().is_empty(), "string_validation");
+impl fmt::Display for StringOperation {
+    // Print the operation the same way it is typed on the command line
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
     }
+}
 
-    fn get_input(input_prompt: String) -> Result<String, Box<dyn Error>> {
-        println!("{}\n", input_prompt);
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-        Ok(input.trim().to_string())
+impl Transform for StringOperation {
+    fn name(&self) -> &str {
+        StringOperation::name(self)
     }
 
-    fn get_valid_input(
-        validators: Vec<fn(&str) -> (bool, &str)>,
-        input_prompt: String,
-    ) -> Result<String, Box<dyn Error>> {
-        let input = get_input(input_prompt)?;
+    fn aliases(&self) -> &[&str] {
+        StringOperation::aliases(self)
+    }
 
-        for validator in validators {
-            let (valid, validation_type) = validator(&input);
+    fn description(&self) -> &str {
+        StringOperation::description(self)
+    }
 
-            if !valid {
-                return Err(format!("Invalid input for validator: {}", validation_type).into());
-            }
+    fn input_hint(&self) -> &str {
+        match self {
+            StringOperation::Csv => "the path to the CSV file",
+            StringOperation::Md5
+            | StringOperation::Sha1
+            | StringOperation::Sha256
+            | StringOperation::Sha512
+            | StringOperation::Blake3
+            | StringOperation::Crc32 => "the string to hash, or the path with --file",
+            _ => "the string to modify",
         }
-        Ok(input)
-    }
-
-    // Define the functions for each modification
-    fn lowercase(input: Option<&str>) -> Result<String, Box<dyn Error>> {
-        match input {
-            Some(input) => Ok(input.to_lowercase()),
-            None => {
-                let input: String = get_valid_input(
-                    vec![is_valid_string],
-                    String::from("Provide the string to modify: "),
-                )?;
-                Ok(input.to_lowercase())
+    }
+
+    fn apply(&self, input: &str, options: &Options) -> Result<String, Error> {
+        // Match the enum variant and call the corresponding function
+        match self {
+            StringOperation::Lowercase => lowercase_in(input, &Locale::from_options(options)?),
+            StringOperation::Uppercase => uppercase_in(input, &Locale::from_options(options)?),
+            StringOperation::NoSpaces if options.flag("all") => cleanup::no_whitespace(input),
+            StringOperation::NoSpaces => no_spaces(input),
+            StringOperation::Slugify => {
+                Ok(slugify_with(input, &SlugOptions::from_options(options)?))
             }
+            StringOperation::Reverse => reverse(input),
+            StringOperation::TitleCase => title_case_in(input, &Locale::from_options(options)?),
+            StringOperation::SnakeCase => snake_case(input),
+            StringOperation::CamelCase => camel_case(input),
+            StringOperation::PascalCase => pascal_case(input),
+            StringOperation::KebabCase => kebab_case(input),
+            StringOperation::ScreamingSnakeCase => screaming_snake_case(input),
+            StringOperation::TrainCase => train_case(input),
+            StringOperation::Nfc => cleanup::nfc(input),
+            StringOperation::Nfd => cleanup::nfd(input),
+            StringOperation::Nfkc => cleanup::nfkc(input),
+            StringOperation::Nfkd => cleanup::nfkd(input),
+            StringOperation::AsciiFold => cleanup::ascii_fold(input),
+            StringOperation::StripInvisible => cleanup::strip_invisible(input),
+            StringOperation::NormalizeWhitespace => {
+                cleanup::normalize_whitespace(input, options.flag("keep-newlines"))
+            }
+            StringOperation::AsciiQuotes => cleanup::ascii_quotes(input),
+            StringOperation::Length => length(input, options),
+            StringOperation::Graphemes => graphemes(input),
+            StringOperation::Wrap => layout::wrap(input, options),
+            StringOperation::Justify => layout::justify(input, options),
+            StringOperation::Center => layout::align(input, options, Align::Center),
+            StringOperation::PadLeft => layout::align(input, options, Align::Right),
+            StringOperation::PadRight => layout::align(input, options, Align::Left),
+            StringOperation::Truncate => layout::truncate(input, options),
+            StringOperation::SortLines => lines::sort_lines(input, options),
+            StringOperation::Uniq => lines::uniq(input, options),
+            StringOperation::Dedupe => lines::dedupe(input, options),
+            StringOperation::Shuffle => lines::shuffle(input, options),
+            StringOperation::NumberLines => lines::number_lines(input, options),
+            StringOperation::ReverseLines => lines::reverse_lines(input),
+            StringOperation::Stats => stats::stats(input, options),
+            StringOperation::Replace => pattern::replace(input, options),
+            StringOperation::Extract => pattern::extract(input, options),
+            // The input is only filtered, see `keep`
+            StringOperation::Matches => Ok(input.to_string()),
+            StringOperation::Base64Encode => encoding::base64_encode(input, options),
+            StringOperation::Base64Decode => encoding::base64_decode(input, options),
+            StringOperation::HexEncode => encoding::hex_encode(input),
+            StringOperation::HexDecode => encoding::hex_decode(input),
+            StringOperation::UrlEncode => encoding::url_encode(input, options),
+            StringOperation::UrlDecode => encoding::url_decode(input, options),
+            StringOperation::HtmlEscape => encoding::html_escape(input),
+            StringOperation::HtmlUnescape => encoding::html_unescape(input),
+            StringOperation::JsonEscape => encoding::json_escape(input),
+            StringOperation::JsonUnescape => encoding::json_unescape(input),
+            StringOperation::Md5 => hash::hash(Algorithm::Md5, input, options),
+            StringOperation::Sha1 => hash::hash(Algorithm::Sha1, input, options),
+            StringOperation::Sha256 => hash::hash(Algorithm::Sha256, input, options),
+            StringOperation::Sha512 => hash::hash(Algorithm::Sha512, input, options),
+            StringOperation::Blake3 => hash::hash(Algorithm::Blake3, input, options),
+            StringOperation::Crc32 => hash::hash(Algorithm::Crc32, input, options),
+            StringOperation::Csv => csv_operations::parse_as_csv(input, options),
         }
     }
 
-    fn uppercase(input: Option<&str>) -> Result<String, Box<dyn Error>> {
-        match input {
-            Some(input) => Ok(input.to_uppercase()),
-            None => {
-                let input: String = get_valid_input(
-                    vec![is_valid_string],
-                    String::from("Provide the string to modify: "),
-                )?;
-                Ok(input.to_uppercase())
-            }
+    fn keep(&self, input: &str, options: &Options) -> Result<bool, Error> {
+        match self {
+            StringOperation::Matches => pattern::is_match(input, options),
+            _ => Ok(true),
         }
     }
 
-    fn no_spaces(input: Option<&str>) -> Result<String, Box<dyn Error>> {
-        match input {
-            Some(input) => Ok(input.replace(" ", "")),
-            None => {
-                let input: String = get_valid_input(
-                    vec![is_valid_string],
-                    String::from("Provide the string to modify: "),
-                )?;
-                Ok(input.replace(" ", ""))
+    fn whole_input(&self) -> bool {
+        matches!(
+            self,
+            StringOperation::SortLines
+                | StringOperation::Uniq
+                | StringOperation::Dedupe
+                | StringOperation::Shuffle
+                | StringOperation::NumberLines
+                | StringOperation::ReverseLines
+        )
+    }
+
+    fn write_to(
+        &self,
+        input: &str,
+        options: &Options,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        match self {
+            StringOperation::Csv => csv_operations::write_csv(input, options, out),
+            _ => {
+                writeln!(out, "{}", self.apply(input, options)?)?;
+                Ok(())
             }
         }
     }
+}
+
+/// One operation of a [`Pipeline`] with the options given to it.
+#[derive(Clone)]
+pub struct Stage {
+    pub transform: Arc<dyn Transform>,
+    pub options: Options,
+}
+
+impl Stage {
+    // Names the stage at 0-based `index` in the error
+    fn error(&self, index: usize, source: Error) -> Error {
+        Error::Stage {
+            index: index + 1,
+            operation: self.transform.name().to_string(),
+            source: Box::new(source),
+        }
+    }
+}
+
+/// Ordered list of transforms, parsed from `<op>[:<options>]|<op>|...`.
+///
+/// `lowercase|no-spaces|reverse` --> `[lowercase, no-spaces, reverse]`
+#[derive(Clone)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+}
 
-    fn slugify_input(input: Option<&str>) -> Result<String, Box<dyn Error>> {
-        match input {
-            Some(input) => Ok(slugify(input)),
-            None => {
-                let input: String = get_valid_input(
-                    vec![is_valid_string],
-                    String::from("Provide the string to modify: "),
-                )?;
-                Ok(slugify(input))
+impl Pipeline {
+    /// Parses the pipeline, looking the stages up in `registry`.
+    pub fn parse(s: &str, registry: &Registry) -> Result<Pipeline, Error> {
+        let mut stages = Vec::new();
+
+        for (i, stage) in split_unquoted(s, '|').into_iter().enumerate() {
+            let (name, spec) = stage.split_once(':').unwrap_or((stage, ""));
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(Error::invalid_argument(format!(
+                    "Stage {} of pipeline '{}' is empty",
+                    i + 1,
+                    s
+                )));
             }
+            let transform = registry.lookup(name)?;
+            let options = Options::from_spec(spec)
+                .map_err(|e| Error::invalid_argument(format!("Stage {}: {}", i + 1, e)))?;
+            stages.push(Stage { transform, options });
         }
+        Ok(Pipeline { stages })
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// Runs the stages in order, each one gets the output of the previous one.
+    /// The error names the stage which failed.
+    pub fn run(&self, input: &str) -> Result<String, Error> {
+        self.run_with(input, &Options::new())
     }
 
-    fn reverse(input: Option<&str>) -> Result<String, Box<dyn Error>> {
-        match input {
-            Some(input) => Ok(input.chars().rev().collect()),
-            None => {
-                let input: String = get_valid_input(
-                    vec![is_valid_string],
-                    String::from("Provide the string to modify: "),
-                )?;
-                Ok(input.chars().rev().collect())
+    /// Like [`Pipeline::run`], `options` are given to every stage. Options of
+    /// the stage itself take precedence.
+    ///
+    /// Input dropped by a filtering stage, like `matches`, gives an empty
+    /// string, see [`Pipeline::filter_with`].
+    pub fn run_with(&self, input: &str, options: &Options) -> Result<String, Error> {
+        Ok(self.filter_with(input, options)?.unwrap_or_default())
+    }
+
+    /// Like [`Pipeline::run_with`], `None` when a stage dropped the input.
+    pub fn filter_with(&self, input: &str, options: &Options) -> Result<Option<String>, Error> {
+        self.filter_stages(0..self.stages.len(), input, options)
+    }
+
+    // Runs only the stages in `range`
+    fn filter_stages(
+        &self,
+        range: Range<usize>,
+        input: &str,
+        options: &Options,
+    ) -> Result<Option<String>, Error> {
+        let mut current = input.to_string();
+
+        for i in range {
+            let stage = &self.stages[i];
+            let options = options.merged(&stage.options);
+            let keep = stage
+                .transform
+                .keep(&current, &options)
+                .map_err(|e| stage.error(i, e))?;
+            if !keep {
+                return Ok(None);
             }
+            current = stage
+                .transform
+                .apply(&current, &options)
+                .map_err(|e| stage.error(i, e))?;
         }
+        Ok(Some(current))
     }
 
-    fn get_title_case(input: &str) -> Result<String, Box<dyn Error>> {
-        Ok(input
-            .split_whitespace()
-            .map(|word| {
-                let mut chars = word.chars();
-                match chars.next() {
-                    None => String::new(),
-                    Some(first_char) => first_char
-                        .to_uppercase()
-                        .chain(chars.flat_map(|c| c.to_lowercase()))
-                        .collect(),
+    /// True when a stage needs all the lines at once, see
+    /// [`Transform::whole_input`].
+    pub fn is_whole_input(&self) -> bool {
+        self.stages
+            .iter()
+            .any(|stage| stage.transform.whole_input())
+    }
+
+    /// Runs the pipeline on the lines of a text, as batch mode does.
+    ///
+    /// Whole input stages, like `sort-lines`, get all the lines, each ended
+    /// with `\n`, the other stages are applied to every line on its own:
+    ///
+    /// `sort-lines|uppercase` on `b`, `a` --> `A`, `B`
+    ///
+    /// When a stage fails on a line, `on_error` gets the 0-based index the
+    /// line had in `lines` and the error, and the line is left as it was.
+    /// After a whole input stage a line has the index of the input line with
+    /// the same text, or of the line at the same position when the stage kept
+    /// the number of lines, like `number-lines`. It is `None` when the line
+    /// can't be traced back, e.g. after `uniq:count`. An error of a whole
+    /// input stage is returned.
+    pub fn run_lines(
+        &self,
+        mut lines: Vec<String>,
+        options: &Options,
+        mut on_error: impl FnMut(Option<usize>, Error),
+    ) -> Result<Vec<String>, Error> {
+        let mut origins: Vec<Option<usize>> = (0..lines.len()).map(Some).collect();
+        let mut start = 0;
+        for end in 0..=self.stages.len() {
+            let whole = self.stages.get(end);
+            if whole.is_some_and(|stage| !stage.transform.whole_input()) {
+                continue;
+            }
+            if start < end {
+                let mut kept = Vec::with_capacity(lines.len());
+                let mut kept_origins = Vec::with_capacity(lines.len());
+                for (line, origin) in lines.into_iter().zip(origins) {
+                    match self.filter_stages(start..end, &line, options) {
+                        Ok(Some(output)) => kept.push(output),
+                        Ok(None) => continue,
+                        Err(e) => {
+                            on_error(origin, e);
+                            kept.push(line);
+                        }
+                    }
+                    kept_origins.push(origin);
                 }
-            })
-            .collect::<Vec<String>>()
-            .join(" "))
-    }
-
-    fn title_case(input: Option<&str>) -> Result<String, Box<dyn Error>> {
-        match input {
-            Some(input) => Ok(get_title_case(input)?),
-            None => {
-                // If no input is provided, ask for it
-                let input: String = get_valid_input(
-                    vec![is_valid_string],
-                    String::from("Provide the string to modify: "),
-                )?;
-                Ok(get_title_case(&input)?)
+                lines = kept;
+                origins = kept_origins;
+            }
+            if let Some(stage) = whole {
+                let options = options.merged(&stage.options);
+                let input: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+                let output = stage
+                    .transform
+                    .apply(&input, &options)
+                    .map_err(|e| stage.error(end, e))?;
+                let output: Vec<String> = output.lines().map(str::to_string).collect();
+                origins = trace_origins(&lines, &origins, &output);
+                lines = output;
+                start = end + 1;
             }
         }
+        Ok(lines)
     }
 
-    pub fn get_modified_input(
-        input: Option<&str>,
-        operation: StringOperation,
-    ) -> Result<String, Box<dyn Error>> {
-        // Match the enum variant and call the corresponding function
-        let modified_input = match operation {
-            StringOperation::Lowercase => lowercase(input),
-            StringOperation::Uppercase => uppercase(input),
-            StringOperation::NoSpaces => no_spaces(input),
-            StringOperation::Slugify => slugify_input(input),
-            StringOperation::Reverse => reverse(input),
-            StringOperation::TitleCase => title_case(input),
-            StringOperation::Csv => csv_operations::parse_as_csv(input),
+    /// Like [`Pipeline::run_with`], the last stage writes its result into `out`.
+    /// Nothing is written when a stage dropped the input.
+    pub fn write_with(
+        &self,
+        input: &str,
+        options: &Options,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let (last, stages) = match self.stages.split_last() {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+        let head = Pipeline {
+            stages: stages.to_vec(),
+        };
+        let current = match head.filter_with(input, options)? {
+            Some(current) => current,
+            None => return Ok(()),
         };
-        Ok(modified_input?)
+        let index = self.stages.len() - 1;
+        let options = options.merged(&last.options);
+        if !last
+            .transform
+            .keep(&current, &options)
+            .map_err(|e| last.error(index, e))?
+        {
+            return Ok(());
+        }
+        last.transform
+            .write_to(&current, &options, out)
+            .map_err(|e| last.error(index, e))
     }
+}
 
-    pub mod csv_operations {
-        use super::{get_valid_input, is_valid_string};
-        use csv::StringRecord;
-        use std::error::Error;
-        use std::fmt;
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.stages.iter().map(|stage| stage.transform.name()))
+            .finish()
+    }
+}
 
-        pub struct CsvRecords {
-            pub headers: StringRecord,
-            pub records: Vec<csv::StringRecord>,
-        }
+// Origins of the lines a whole input stage returned, see `Pipeline::run_lines`
+fn trace_origins(
+    before: &[String],
+    origins: &[Option<usize>],
+    after: &[String],
+) -> Vec<Option<usize>> {
+    let mut unused: HashMap<&str, VecDeque<Option<usize>>> = HashMap::new();
+    for (line, origin) in before.iter().zip(origins) {
+        unused.entry(line).or_default().push_back(*origin);
+    }
+    after
+        .iter()
+        .enumerate()
+        .map(
+            |(i, line)| match unused.get_mut(line.as_str()).and_then(VecDeque::pop_front) {
+                Some(origin) => origin,
+                None if before.len() == after.len() => origins[i],
+                None => None,
+            },
+        )
+        .collect()
+}
 
-        impl fmt::Display for CsvRecords {
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                let mut column_widths = vec![0; self.headers.len()];
+impl FromStr for Pipeline {
+    type Err = Error;
 
-                for (i, header) in self.headers.iter().enumerate() {
-                    column_widths[i] = header.len();
-                }
+    /// Parses the pipeline using the builtin operations only.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pipeline::parse(s, &Registry::default())
+    }
+}
 
-                for record in &self.records {
-                    for (i, field) in record.iter().enumerate() {
-                        if field.len() > column_widths[i] {
-                            column_widths[i] = field.len();
-                        }
-                    }
-                }
+// Define the functions for each modification
 
-                let format_row = |row: &StringRecord| {
-                    row.iter()
-                        .enumerate()
-                        .map(|(i, field)| format!("{:width$}", field, width = column_widths[i]))
-                        .collect::<Vec<String>>()
-                        .join(" | ")
-                };
-
-                writeln!(f, "{}", format_row(&self.headers))?;
-                writeln!(
-                    f,
-                    "{}",
-                    column_widths
-                        .iter()
-                        .map(|&w| "-".repeat(w))
-                        .collect::<Vec<String>>()
-                        .join("-+-")
-                )?;
-
-                // Print records
-                for record in &self.records {
-                    writeln!(f, "{}", format_row(record))?;
-                }
+/// `Hello World` --> `hello world`
+pub fn lowercase(input: &str) -> Result<String, Error> {
+    lowercase_in(input, &Locale::root())
+}
 
-                Ok(())
-            }
-        }
+/// Like [`lowercase`] with the case mapping of the language,
+/// `İSTANBUL` --> `istanbul` in Turkish.
+pub fn lowercase_in(input: &str, locale: &Locale) -> Result<String, Error> {
+    Ok(locale.to_lowercase(input))
+}
+
+/// `Hello World` --> `HELLO WORLD`
+pub fn uppercase(input: &str) -> Result<String, Error> {
+    uppercase_in(input, &Locale::root())
+}
+
+/// Like [`uppercase`] with the case mapping of the language,
+/// `istanbul` --> `İSTANBUL` in Turkish.
+pub fn uppercase_in(input: &str, locale: &Locale) -> Result<String, Error> {
+    Ok(locale.to_uppercase(input))
+}
+
+/// `Hello World` --> `HelloWorld`
+pub fn no_spaces(input: &str) -> Result<String, Error> {
+    Ok(input.replace(' ', ""))
+}
+
+/// `Hello World` --> `hello-world`
+///
+/// See [`slugify_with`] for the separator, length and stop words.
+pub fn slugify_input(input: &str) -> Result<String, Error> {
+    Ok(slugify_with(input, &SlugOptions::default()))
+}
+
+/// `Hello World` --> `dlroW olleH`
+///
+/// Grapheme clusters are kept together, so combining accents stay on their
+/// letter and emoji sequences aren't torn apart.
+pub fn reverse(input: &str) -> Result<String, Error> {
+    Ok(input.graphemes(true).rev().collect())
+}
+
+/// Lengths of the input in every unit, or only the one given by the `unit`
+/// option.
+///
+/// `Žluť` --> `bytes: 6\ncode points: 4\ngraphemes: 4\nwidth: 4`
+pub fn length(input: &str, options: &Options) -> Result<String, Error> {
+    let lengths = Lengths::of(input);
+    match options.get("unit") {
+        None => Ok(lengths.to_string()),
+        Some(unit) => lengths.get(unit).map(|n| n.to_string()).ok_or_else(|| {
+            Error::invalid_argument(format!(
+                "Unknown unit: {}\nAvailable units: {}",
+                unit,
+                UNITS.join(", ")
+            ))
+        }),
+    }
+}
 
-        fn process_csv_from_reader(
-            reader: &mut csv::Reader<std::fs::File>,
-        ) -> Result<String, Box<dyn Error>> {
-            let mut records: Vec<csv::StringRecord> = Vec::new();
+/// Table with one row per grapheme cluster: the cluster, its code points,
+/// bytes and display width.
+pub fn graphemes(input: &str) -> Result<String, Error> {
+    let records = input
+        .graphemes(true)
+        .map(|grapheme| {
+            let lengths = Lengths::of(grapheme);
+            let code_points: Vec<String> = grapheme
+                .chars()
+                .map(|c| format!("U+{:04X}", c as u32))
+                .collect();
+            // Control characters would break the table
+            let shown: String = if grapheme.chars().any(char::is_control) {
+                grapheme.escape_debug().collect()
+            } else {
+                grapheme.to_string()
+            };
+            StringRecord::from(vec![
+                shown,
+                code_points.join(" "),
+                lengths.bytes.to_string(),
+                lengths.width.to_string(),
+            ])
+        })
+        .collect();
+    let table = CsvRecords {
+        headers: StringRecord::from(vec!["grapheme", "code points", "bytes", "width"]),
+        records,
+    };
+    Ok(table.to_string().trim_end().to_string())
+}
+
+/// `hELLO wORLD` --> `Hello World`
+pub fn title_case(input: &str) -> Result<String, Error> {
+    title_case_in(input, &Locale::root())
+}
 
-            for result in reader.records() {
-                let record = result?;
-                records.push(record);
+/// Like [`title_case`], the small words of the language stay in lowercase
+/// unless they are the first or the last word.
+///
+/// `the lord of the rings` --> `The Lord of the Rings` in English
+pub fn title_case_in(input: &str, locale: &Locale) -> Result<String, Error> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    Ok(words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let inner = i > 0 && i + 1 < words.len();
+            if inner && locale.is_small_word(word) {
+                locale.to_lowercase(word)
+            } else {
+                locale.capitalize(word)
             }
-            let headers = reader.headers()?.clone();
-            let csv_records = CsvRecords { headers, records };
+        })
+        .collect::<Vec<String>>()
+        .join(" "))
+}
+
+/// `HTTPServer2Config` --> `http_server2_config`
+pub fn snake_case(input: &str) -> Result<String, Error> {
+    Ok(join_words(input, "_", str::to_lowercase))
+}
+
+/// `http_server2_config` --> `httpServer2Config`
+pub fn camel_case(input: &str) -> Result<String, Error> {
+    let pascal = pascal_case(input)?;
+    let mut chars = pascal.chars();
+    Ok(match chars.next() {
+        None => String::new(),
+        Some(first_char) => first_char.to_lowercase().chain(chars).collect(),
+    })
+}
+
+/// `http_server2_config` --> `HttpServer2Config`
+pub fn pascal_case(input: &str) -> Result<String, Error> {
+    Ok(join_words(input, "", capitalize))
+}
+
+/// `HTTPServer2Config` --> `http-server2-config`
+pub fn kebab_case(input: &str) -> Result<String, Error> {
+    Ok(join_words(input, "-", str::to_lowercase))
+}
+
+/// `HTTPServer2Config` --> `HTTP_SERVER2_CONFIG`
+pub fn screaming_snake_case(input: &str) -> Result<String, Error> {
+    Ok(join_words(input, "_", str::to_uppercase))
+}
+
+/// `HTTPServer2Config` --> `Http-Server2-Config`
+pub fn train_case(input: &str) -> Result<String, Error> {
+    Ok(join_words(input, "-", capitalize))
+}
+
+// Splits the input into words and joins them back converted
+fn join_words(input: &str, separator: &str, convert: fn(&str) -> String) -> String {
+    words(input)
+        .into_iter()
+        .map(convert)
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// Applies one operation to the input with the default options.
+///
+/// For [`StringOperation::Csv`] the input is the path to the CSV file.
+pub fn get_modified_input(input: &str, operation: StringOperation) -> Result<String, Error> {
+    operation.apply(input, &Options::new())
+}
 
-            Ok(csv_records.to_string())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_operation_parses_and_prints_back() {
+        for operation in StringOperation::ALL {
+            assert_eq!(
+                StringOperation::from_str(operation.name()).unwrap(),
+                *operation
+            );
+            assert_eq!(operation.to_string(), operation.name());
         }
+        assert_eq!(
+            StringOperation::from_str("title").unwrap(),
+            StringOperation::TitleCase
+        );
+        assert!(StringOperation::from_str("shout").is_err());
+    }
 
-        pub fn parse_as_csv(file_path: Option<&str>) -> Result<String, Box<dyn Error>> {
-            match file_path {
-                Some(file_path) => {
-                    let mut rdr = csv::Reader::from_path(file_path)?;
-                    Ok(process_csv_from_reader(&mut rdr)?)
-                }
-                None => {
-                    match get_valid_input(
-                        vec![is_valid_string],
-                        "Please insert the path to the CSV file:".to_string(),
-                    ) {
-                        Ok(input) => {
-                            print!("Reading CSV file... {}\n", &input);
-                            let mut rdr = csv::Reader::from_path(&input)?;
-                            Ok(process_csv_from_reader(&mut rdr)?)
-                        }
-                        Err(e) => return Err(e.into()),
-                    }
+    #[test]
+    fn string_transforms() {
+        assert_eq!(lowercase("Hello World").unwrap(), "hello world");
+        assert_eq!(uppercase("Hello World").unwrap(), "HELLO WORLD");
+        assert_eq!(no_spaces("Hello  World ").unwrap(), "HelloWorld");
+        let all = Options::from_spec("all").unwrap();
+        assert_eq!(
+            StringOperation::NoSpaces
+                .apply("Hello\u{a0}World\t!", &all)
+                .unwrap(),
+            "HelloWorld!"
+        );
+        assert_eq!(
+            StringOperation::NoSpaces
+                .apply("Hello\u{a0}World", &Options::new())
+                .unwrap(),
+            "Hello\u{a0}World"
+        );
+        assert_eq!(slugify_input("Hello World!").unwrap(), "hello-world");
+        assert_eq!(reverse("Hello").unwrap(), "olleH");
+        assert_eq!(reverse("ae\u{301}🇨🇿").unwrap(), "🇨🇿e\u{301}a");
+        assert_eq!(
+            reverse("👨\u{200d}👩\u{200d}👧!").unwrap(),
+            "!👨\u{200d}👩\u{200d}👧"
+        );
+        assert_eq!(title_case("hELLO   wORLD").unwrap(), "Hello World");
+    }
+
+    #[test]
+    fn locale_option() {
+        let run = |operation: StringOperation, input: &str, locale: &str| {
+            let options = Options::from_spec(&format!("locale={}", locale)).unwrap();
+            operation.apply(input, &options).unwrap()
+        };
+        assert_eq!(
+            run(StringOperation::Uppercase, "diyarbakır", "tr"),
+            "DİYARBAKIR"
+        );
+        assert_eq!(
+            run(StringOperation::Lowercase, "DİYARBAKIR", "az"),
+            "diyarbakır"
+        );
+        assert_eq!(
+            run(StringOperation::TitleCase, "the lord OF THE rings", "en"),
+            "The Lord of the Rings"
+        );
+        assert_eq!(
+            run(
+                StringOperation::TitleCase,
+                "procházka v lese a u řeky",
+                "cs"
+            ),
+            "Procházka v Lese a u Řeky"
+        );
+        assert_eq!(
+            run(StringOperation::TitleCase, "what we are made of", "en"),
+            "What We Are Made Of"
+        );
+        assert_eq!(
+            title_case("the lord of the rings").unwrap(),
+            "The Lord Of The Rings"
+        );
+        let options = Options::from_spec("locale=x").unwrap();
+        assert!(StringOperation::Lowercase.apply("A", &options).is_err());
+    }
+
+    #[test]
+    fn lengths_and_graphemes() {
+        let options = Options::from_spec("unit=graphemes").unwrap();
+        assert_eq!(length("e\u{301}🇨🇿", &options).unwrap(), "2");
+        assert_eq!(
+            length("Žluť", &Options::new()).unwrap(),
+            "bytes: 6\ncode points: 4\ngraphemes: 4\nwidth: 4"
+        );
+        let options = Options::from_spec("unit=lines").unwrap();
+        assert_eq!(
+            length("x", &options).unwrap_err().kind(),
+            "invalid_argument"
+        );
+
+        assert_eq!(
+            graphemes("e\u{301}東\t").unwrap(),
+            "grapheme | code points   | bytes | width\n\
+             ---------+---------------+-------+------\n\
+             e\u{301}        | U+0065 U+0301 |     3 |     1\n\
+             東       | U+6771        |     3 |     2\n\
+             \\t       | U+0009        |     1 |     1"
+        );
+    }
+
+    #[test]
+    fn identifier_cases() {
+        let input = "HTTPServer2Config";
+        assert_eq!(snake_case(input).unwrap(), "http_server2_config");
+        assert_eq!(camel_case(input).unwrap(), "httpServer2Config");
+        assert_eq!(pascal_case(input).unwrap(), "HttpServer2Config");
+        assert_eq!(kebab_case(input).unwrap(), "http-server2-config");
+        assert_eq!(screaming_snake_case(input).unwrap(), "HTTP_SERVER2_CONFIG");
+        assert_eq!(train_case(input).unwrap(), "Http-Server2-Config");
+        assert_eq!(camel_case("  user id ").unwrap(), "userId");
+        assert_eq!(snake_case("").unwrap(), "");
+    }
+
+    type Case = fn(&str) -> Result<String, Error>;
+
+    #[test]
+    fn identifier_cases_round_trip() {
+        let cases: [Case; 6] = [
+            snake_case,
+            camel_case,
+            pascal_case,
+            kebab_case,
+            screaming_snake_case,
+            train_case,
+        ];
+        let identifiers = [
+            "id",
+            "userId",
+            "HTTPServer2Config",
+            "parse_json_file",
+            "max-retry-count",
+            "MAX_RETRY_COUNT",
+            "Content-Type",
+            "ÚčetníDoklad",
+            "version2Beta3",
+        ];
+        for identifier in identifiers {
+            for from in cases {
+                let expected = from(identifier).unwrap();
+                for to in cases {
+                    let converted = to(&expected).unwrap();
+                    assert_eq!(
+                        from(&converted).unwrap(),
+                        expected,
+                        "{} via {}",
+                        identifier,
+                        converted
+                    );
                 }
             }
         }
     }
+
+    #[test]
+    fn pipeline_runs_stages_in_order() {
+        let pipeline = Pipeline::from_str("lowercase | no-spaces|reverse").unwrap();
+        assert_eq!(
+            format!("{:?}", pipeline),
+            r#"["lowercase", "no-spaces", "reverse"]"#
+        );
+        assert_eq!(pipeline.run("Hello World").unwrap(), "dlrowolleh");
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let input = "Žluťoučký kůň <úpěl> & \"ódy\"?\n";
+        for pair in [
+            "base64-encode|base64-decode",
+            "b64enc:url-safe,no-pad|b64dec:url-safe",
+            "hex-encode|hex-decode",
+            "url-encode|url-decode",
+            "url-encode:full|url-decode",
+            "html-escape|html-unescape",
+            "json-escape|json-unescape",
+        ] {
+            let pipeline = Pipeline::from_str(pair).unwrap();
+            assert_eq!(pipeline.run(input).unwrap(), input, "{}", pair);
+        }
+        let err = Pipeline::from_str("base64-decode")
+            .unwrap()
+            .run("not base64!")
+            .unwrap_err();
+        assert_eq!(err.kind(), "transform");
+        assert!(err
+            .to_string()
+            .starts_with("Stage 1 (base64-decode) failed: Invalid base64"));
+    }
+
+    #[test]
+    fn filtering_stages_drop_the_input() {
+        let pipeline = Pipeline::from_str("matches:pattern=^a|uppercase").unwrap();
+        let options = Options::new();
+        assert_eq!(
+            pipeline.filter_with("abc", &options).unwrap(),
+            Some("ABC".to_string())
+        );
+        assert_eq!(pipeline.filter_with("xyz", &options).unwrap(), None);
+        assert_eq!(pipeline.run("xyz").unwrap(), "");
+
+        let mut out = Vec::new();
+        pipeline.write_with("xyz", &options, &mut out).unwrap();
+        assert!(out.is_empty());
+        let pipeline = Pipeline::from_str("uppercase|grep:pattern=A").unwrap();
+        pipeline.write_with("abc", &options, &mut out).unwrap();
+        assert_eq!(out, b"ABC\n");
+    }
+
+    #[test]
+    fn whole_input_stages_get_every_line() {
+        let lines = |lines: &[&str]| lines.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let pipeline = Pipeline::from_str("grep:pattern=^[a-z]|sort-lines|rev|nl").unwrap();
+        assert!(pipeline.is_whole_input());
+        assert!(!Pipeline::from_str("upper|rev").unwrap().is_whole_input());
+
+        let mut errors = Vec::new();
+        let output = pipeline
+            .run_lines(
+                lines(&["pear", "Fig", "apple", ""]),
+                &Options::new(),
+                |i, e| errors.push((i, e.kind())),
+            )
+            .unwrap();
+        assert_eq!(output, lines(&["1 elppa", "2 raep"]));
+        assert!(errors.is_empty());
+
+        // Errors name the line of the input, not the one after dedupe or sort
+        let pipeline = Pipeline::from_str("dedupe|b64dec|sort").unwrap();
+        let output = pipeline
+            .run_lines(
+                lines(&["YQ==", "YQ==", "!", ""]),
+                &Options::new(),
+                |i, e| errors.push((i, e.kind())),
+            )
+            .unwrap();
+        assert_eq!(output, lines(&["", "!", "a"]));
+        assert_eq!(errors, [(Some(2), "transform")]);
+
+        errors.clear();
+        let pipeline = Pipeline::from_str("sort-lines|b64dec").unwrap();
+        pipeline
+            .run_lines(lines(&["YQ==", "!", "Yg=="]), &Options::new(), |i, e| {
+                errors.push((i, e.kind()))
+            })
+            .unwrap();
+        assert_eq!(errors, [(Some(1), "transform")]);
+
+        // Numbered lines keep their position, counted ones can't be traced
+        let cases = [
+            (
+                "nl|b64dec",
+                ["a", "!", "c"],
+                vec![Some(0), Some(1), Some(2)],
+            ),
+            ("uniq:count|b64dec", ["a", "a", "!"], vec![None, None]),
+        ];
+        for (pipeline, input, origins) in cases {
+            errors.clear();
+            Pipeline::from_str(pipeline)
+                .unwrap()
+                .run_lines(lines(&input), &Options::new(), |i, e| {
+                    errors.push((i, e.kind()))
+                })
+                .unwrap();
+            let failed: Vec<Option<usize>> = errors.iter().map(|(i, _)| *i).collect();
+            assert_eq!(failed, origins);
+        }
+        // The same text as one string
+        assert_eq!(
+            Pipeline::from_str("sort|upper")
+                .unwrap()
+                .run("b\na")
+                .unwrap(),
+            "A\nB"
+        );
+    }
+
+    #[test]
+    fn pipeline_reports_failing_stage() {
+        let err = Pipeline::from_str("lowercase|shout").unwrap_err();
+        assert!(matches!(err, Error::UnknownOperation { ref name, .. } if name == "shout"));
+        let err = Pipeline::from_str("lowercase||reverse").unwrap_err();
+        assert_eq!(err.kind(), "invalid_argument");
+
+        let pipeline = Pipeline::from_str("lowercase|csv").unwrap();
+        let err = pipeline.run("/does/not/exist.csv").unwrap_err();
+        assert!(matches!(err, Error::Stage { index: 2, .. }));
+        assert_eq!(err.kind(), "io");
+        assert!(err.to_string().starts_with("Stage 2 (csv) failed:"));
+    }
 }
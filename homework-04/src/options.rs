@@ -0,0 +1,261 @@
+//! Options passed to transforms.
+//!
+//! On the command line they are given as `--key value` (or `--key=value`)
+//! after the pipeline, in a pipeline stage as `name:key=value,key=value`.
+//! Options without a value are flags. Values containing `,` or `|` can be
+//! wrapped in single or double quotes:
+//!
+//! ```text
+//! csv:select='name,age',sort=-age data.csv
+//! ```
+
+use crate::error::Error;
+use std::str::FromStr;
+
+/// Ordered list of `key=value` pairs, a key may be repeated.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    values: Vec<(String, String)>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// Adds the value, keeping the earlier values of the same key.
+    pub fn push(&mut self, key: &str, value: &str) {
+        self.values.push((key.to_string(), value.to_string()));
+    }
+
+    /// Replaces all values of `key` with `value`.
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.values.retain(|(k, _)| k != key);
+        self.push(key, value);
+    }
+
+    /// The last value given for `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// All values given for `key`, in order.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.values
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Items of the comma separated lists given for `key`, the option can be
+    /// repeated. Empty items are skipped.
+    pub fn get_list<'a>(&'a self, key: &'a str) -> Vec<&'a str> {
+        self.get_all(key)
+            .flat_map(|value| value.split(','))
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+
+    /// True when the option is present and not `false`, `no` or `0`.
+    pub fn flag(&self, key: &str) -> bool {
+        !matches!(self.get(key), None | Some("false") | Some("no") | Some("0"))
+    }
+
+    /// Parses the value of `key`, `None` when the option is missing.
+    pub fn parse<T: FromStr>(&self, key: &str) -> Result<Option<T>, Error> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                Error::invalid_argument(format!("Invalid value '{}' for option '{}'", value, key))
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// These options followed by `overrides`, so the overrides win in [`Options::get`].
+    pub fn merged(&self, overrides: &Options) -> Options {
+        let mut values = self.values.clone();
+        values.extend(overrides.values.iter().cloned());
+        Options { values }
+    }
+
+    /// Parses `--key value`, `--key=value` and `--flag` command line arguments.
+    pub fn from_args(args: &[String]) -> Result<Options, Error> {
+        let mut options = Options::new();
+        let mut args = args.iter().peekable();
+
+        while let Some(arg) = args.next() {
+            let key = match arg.strip_prefix("--") {
+                Some(key) if !key.is_empty() => key,
+                _ => {
+                    return Err(Error::invalid_argument(format!(
+                        "Unexpected argument '{}', expected --<option>",
+                        arg
+                    )))
+                }
+            };
+            if let Some((key, value)) = key.split_once('=') {
+                options.push(key, value);
+                continue;
+            }
+            match args.peek() {
+                Some(value) if !value.starts_with("--") => {
+                    options.push(key, value);
+                    args.next();
+                }
+                _ => options.push(key, "true"),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Parses the `key=value,key=value` part of a pipeline stage.
+    pub fn from_spec(spec: &str) -> Result<Options, Error> {
+        let mut options = Options::new();
+
+        for part in split_unquoted(spec, ',') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            match part.split_once('=') {
+                Some((key, value)) if !key.trim().is_empty() => {
+                    options.push(key.trim(), unquote(value.trim()))
+                }
+                Some(_) => {
+                    return Err(Error::invalid_argument(format!(
+                        "Option '{}' has no name",
+                        part
+                    )))
+                }
+                None => options.push(part, "true"),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Splits `s` on `separator`, except inside single or double quotes.
+pub fn split_unquoted(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == separator => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            None => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Splits `s` at the first whitespace which is not inside quotes, the rest
+/// is returned as it is.
+///
+/// `replace:with='a b' some input` --> `("replace:with='a b'", "some input")`
+pub fn split_command(s: &str) -> (&str, &str) {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c.is_whitespace() => return (&s[..i], &s[i + c.len_utf8()..]),
+            None => {}
+        }
+    }
+    (s, "")
+}
+
+/// Removes one pair of matching quotes around `s`.
+pub fn unquote(s: &str) -> &str {
+    for quote in ['\'', '"'] {
+        if s.len() >= 2 && s.starts_with(quote) && s.ends_with(quote) {
+            return &s[1..s.len() - 1];
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_command_line_arguments() {
+        let options =
+            Options::from_args(&args(&["--sort", "-age", "--where=age>30", "--desc"])).unwrap();
+        assert_eq!(options.get("sort"), Some("-age"));
+        assert_eq!(options.get("where"), Some("age>30"));
+        assert!(options.flag("desc"));
+        assert!(!options.flag("missing"));
+        assert!(Options::from_args(&args(&["data.csv"])).is_err());
+    }
+
+    #[test]
+    fn parses_stage_spec_with_quotes() {
+        let options =
+            Options::from_spec("select='name,age',where=city=Prague,where=age>3,x").unwrap();
+        assert_eq!(options.get("select"), Some("name,age"));
+        assert_eq!(
+            options.get_all("where").collect::<Vec<_>>(),
+            ["city=Prague", "age>3"]
+        );
+        assert!(options.flag("x"));
+        assert!(Options::from_spec("=3").is_err());
+
+        let options = Options::from_spec("words='a,b',words=c,words=,x").unwrap();
+        assert_eq!(options.get_list("words"), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn later_options_win() {
+        let mut global = Options::new();
+        global.push("width", "40");
+        let mut stage = Options::new();
+        stage.push("width", "10");
+        assert_eq!(
+            global.merged(&stage).parse::<usize>("width").unwrap(),
+            Some(10)
+        );
+        let err = Options::from_spec("width=x")
+            .unwrap()
+            .parse::<usize>("width")
+            .unwrap_err();
+        assert_eq!(err.kind(), "invalid_argument");
+    }
+
+    #[test]
+    fn split_keeps_quoted_separators() {
+        assert_eq!(split_unquoted("a|'b|c'|d", '|'), ["a", "'b|c'", "d"]);
+        assert_eq!(unquote("\"a,b\""), "a,b");
+        assert_eq!(
+            split_command("replace:with='a b' some  input"),
+            ("replace:with='a b'", "some  input")
+        );
+        assert_eq!(split_command("lowercase"), ("lowercase", ""));
+    }
+}
@@ -0,0 +1,161 @@
+//! Regex based operations: `replace`, `extract` and `matches`.
+//!
+//! The regex is given by the `pattern` option, `ignore-case` makes it case
+//! insensitive. On the command line and in the interactive mode:
+//!
+//! ```text
+//! homework-04 replace --pattern '(\w+)@(\w+)' --with '$2 at $1'
+//! replace:pattern='(\d+)-(\d+)',with='$2-$1',first 10-20 30-40
+//! ```
+//!
+//! The regex syntax is the one of the [`regex`] crate.
+
+use crate::error::Error;
+use crate::options::Options;
+use regex::{Regex, RegexBuilder};
+use std::cell::RefCell;
+
+thread_local! {
+    // Batch mode runs the same pipeline on every line, the regex is
+    // compiled once per thread instead of once per line
+    static LAST_REGEX: RefCell<Option<(String, bool, Regex)>> = const { RefCell::new(None) };
+}
+
+/// The regex of the `pattern` and `ignore-case` options.
+pub fn regex_from_options(options: &Options) -> Result<Regex, Error> {
+    let pattern = options
+        .get("pattern")
+        .ok_or_else(|| Error::invalid_argument("The pattern option is missing"))?;
+    let ignore_case = options.flag("ignore-case");
+
+    LAST_REGEX.with(|last| {
+        if let Some((last_pattern, last_ignore_case, regex)) = &*last.borrow() {
+            if last_pattern == pattern && *last_ignore_case == ignore_case {
+                return Ok(regex.clone());
+            }
+        }
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|e| Error::invalid_argument(format!("Invalid pattern: {}", e)))?;
+        *last.borrow_mut() = Some((pattern.to_string(), ignore_case, regex.clone()));
+        Ok(regex)
+    })
+}
+
+/// Replaces every match with the `with` option, only the first one with the
+/// `first` flag. `$1` and `${name}` in the replacement are capture groups.
+///
+/// `john@example` with `(\w+)@(\w+)` and `$2 at $1` --> `example at john`
+pub fn replace(input: &str, options: &Options) -> Result<String, Error> {
+    let regex = regex_from_options(options)?;
+    let replacement = options.get("with").unwrap_or("");
+    let replaced = if options.flag("first") {
+        regex.replace(input, replacement)
+    } else {
+        regex.replace_all(input, replacement)
+    };
+    Ok(replaced.into_owned())
+}
+
+/// Every match on its own line. With named groups the line has a
+/// `name=value` pair for every group, separated by tabs. The `group` option
+/// prints only that group, given by name or number.
+///
+/// `2024-10-18` with `(?<year>\d+)-(?<month>\d+)` --> `year=2024\tmonth=10`
+pub fn extract(input: &str, options: &Options) -> Result<String, Error> {
+    let regex = regex_from_options(options)?;
+    let names: Vec<&str> = regex.capture_names().flatten().collect();
+    let group = options.get("group");
+    if let Some(group) = group {
+        let known = match group.parse::<usize>() {
+            Ok(index) => index < regex.captures_len(),
+            Err(_) => names.contains(&group),
+        };
+        if !known {
+            return Err(Error::invalid_argument(format!(
+                "The pattern has no group '{}'",
+                group
+            )));
+        }
+    }
+
+    let lines: Vec<String> = regex
+        .captures_iter(input)
+        .map(|captures| {
+            let text = |m: Option<regex::Match>| m.map_or("", |m| m.as_str()).to_string();
+            match group {
+                Some(group) => match group.parse::<usize>() {
+                    Ok(index) => text(captures.get(index)),
+                    Err(_) => text(captures.name(group)),
+                },
+                None if names.is_empty() => text(captures.get(0)),
+                None => names
+                    .iter()
+                    .map(|name| format!("{}={}", name, text(captures.name(name))))
+                    .collect::<Vec<String>>()
+                    .join("\t"),
+            }
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+/// True when the pattern matches the input, with the `invert` flag when it
+/// doesn't.
+pub fn is_match(input: &str, options: &Options) -> Result<bool, Error> {
+    let regex = regex_from_options(options)?;
+    Ok(regex.is_match(input) != options.flag("invert"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(spec: &str) -> Options {
+        Options::from_spec(spec).unwrap()
+    }
+
+    #[test]
+    fn replaces_with_capture_groups() {
+        let swap = options(r"pattern='(\d+)-(\d+)',with='$2-$1'");
+        assert_eq!(replace("10-20 30-40", &swap).unwrap(), "20-10 40-30");
+        let first = options(r"pattern='(\d+)-(\d+)',with='$2-$1',first");
+        assert_eq!(replace("10-20 30-40", &first).unwrap(), "20-10 30-40");
+        let named = options(r"pattern='(?<user>\w+)@',with='${user} at ',ignore-case");
+        assert_eq!(replace("John@Example", &named).unwrap(), "John at Example");
+        assert_eq!(replace("aAa", &options("pattern=a")).unwrap(), "A");
+        assert_eq!(
+            replace("aAa", &options("pattern=a,ignore-case")).unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn extracts_matches_and_groups() {
+        let numbers = options(r"pattern='\d+'");
+        assert_eq!(extract("a1 b22 c333", &numbers).unwrap(), "1\n22\n333");
+        let dates = options(r"pattern='(?<year>\d{4})-(?<month>\d{2})'");
+        assert_eq!(
+            extract("2024-10 and 2025-01", &dates).unwrap(),
+            "year=2024\tmonth=10\nyear=2025\tmonth=01"
+        );
+        let month = options(r"pattern='(?<year>\d{4})-(?<month>\d{2})',group=month");
+        assert_eq!(extract("2024-10", &month).unwrap(), "10");
+        let second = options(r"pattern='(\d{4})-(\d{2})',group=2");
+        assert_eq!(extract("2024-10", &second).unwrap(), "10");
+        let missing = options(r"pattern='(\d{4})',group=day");
+        assert!(extract("2024", &missing).is_err());
+        assert_eq!(extract("none", &numbers).unwrap(), "");
+    }
+
+    #[test]
+    fn matches_and_invalid_patterns() {
+        assert!(is_match("Hello", &options("pattern=^h,ignore-case")).unwrap());
+        assert!(!is_match("Hello", &options("pattern=^h")).unwrap());
+        assert!(is_match("Hello", &options("pattern=^h,invert")).unwrap());
+        let err = is_match("x", &options("pattern='('")).unwrap_err();
+        assert_eq!(err.kind(), "invalid_argument");
+        assert!(is_match("x", &Options::new()).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+// Pool of worker threads taking jobs from a bounded queue.
+//
+// Every job gets a sequence number when it is submitted. The results are
+// handed to the sink on a separate thread, either in submission order or as
+// soon as they are ready. A job whose work panics gets the panic message as
+// its result, so the jobs submitted after it are not kept waiting.
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+pub struct WorkerPool<J> {
+    sender: SyncSender<(u64, J)>,
+    workers: Vec<JoinHandle<()>>,
+    sink: JoinHandle<()>,
+    next_sequence: u64,
+}
+
+impl<J: Send + 'static> WorkerPool<J> {
+    // `jobs` workers run `work`, at most `queue` jobs wait for a worker
+    pub fn new<R, W, S>(jobs: usize, queue: usize, ordered: bool, work: W, mut sink: S) -> Self
+    where
+        R: Send + 'static,
+        W: Fn(J) -> R + Send + Sync + 'static,
+        S: FnMut(u64, Result<R, String>) + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::sync_channel::<(u64, J)>(queue);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let (result_sender, result_receiver) = mpsc::channel::<(u64, Result<R, String>)>();
+        let work = Arc::new(work);
+
+        let workers = (0..jobs.max(1))
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let result_sender = result_sender.clone();
+                let work = Arc::clone(&work);
+                thread::spawn(move || {
+                    // The lock is released before the job runs
+                    while let Some((sequence, job)) = next_job(&receiver) {
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| work(job)))
+                            .map_err(panic_message);
+                        if result_sender.send((sequence, result)).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(result_sender);
+
+        let sink = thread::spawn(move || {
+            if !ordered {
+                for (sequence, result) in result_receiver {
+                    sink(sequence, result);
+                }
+                return;
+            }
+            // Results which finished before the ones submitted earlier wait here
+            let mut pending = BTreeMap::new();
+            let mut next = 0;
+            for (sequence, result) in result_receiver {
+                pending.insert(sequence, result);
+                while let Some(result) = pending.remove(&next) {
+                    sink(next, result);
+                    next += 1;
+                }
+            }
+        });
+
+        WorkerPool {
+            sender,
+            workers,
+            sink,
+            next_sequence: 0,
+        }
+    }
+
+    // Blocks while the queue is full
+    pub fn submit(&mut self, job: J) -> u64 {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.sender
+            .send((sequence, job))
+            .expect("workers run until the pool is finished");
+        sequence
+    }
+
+    // Waits until all the submitted jobs are done and their results handed to the sink
+    pub fn finish(self) {
+        drop(self.sender);
+        for worker in self.workers {
+            worker.join().unwrap();
+        }
+        self.sink.join().unwrap();
+    }
+}
+
+fn next_job<J>(receiver: &Mutex<Receiver<(u64, J)>>) -> Option<(u64, J)> {
+    receiver.lock().unwrap().recv().ok()
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "the job panicked".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Sender;
+
+    // The jobs wait until the test lets them finish, job `i` on `gates[i]`
+    type Results = Receiver<(u64, Result<u64, String>)>;
+
+    fn pool(ordered: bool, jobs: u64) -> (WorkerPool<u64>, Vec<Sender<()>>, Results) {
+        let (gates, waits): (Vec<_>, Vec<_>) = (0..jobs).map(|_| mpsc::channel::<()>()).unzip();
+        let waits = Mutex::new(waits.into_iter().map(Some).collect::<Vec<_>>());
+        let (result_sender, results) = mpsc::channel();
+        let mut pool = WorkerPool::new(
+            jobs as usize,
+            2,
+            ordered,
+            move |job: u64| {
+                let wait = waits.lock().unwrap()[job as usize].take().unwrap();
+                wait.recv().unwrap();
+                if job == 1 {
+                    panic!("job 1 failed");
+                }
+                job * 10
+            },
+            move |sequence, result| result_sender.send((sequence, result)).unwrap(),
+        );
+        for job in 0..jobs {
+            pool.submit(job);
+        }
+        (pool, gates, results)
+    }
+
+    #[test]
+    fn ordered_results_follow_submission() {
+        let (pool, gates, results) = pool(true, 4);
+        for job in [3, 2, 0] {
+            gates[job].send(()).unwrap();
+        }
+        assert_eq!(results.recv().unwrap(), (0, Ok(0)));
+        // A panic doesn't hold back the results submitted after it
+        gates[1].send(()).unwrap();
+        pool.finish();
+        assert_eq!(
+            results.iter().collect::<Vec<_>>(),
+            [
+                (1, Err("job 1 failed".to_string())),
+                (2, Ok(20)),
+                (3, Ok(30))
+            ]
+        );
+    }
+
+    #[test]
+    fn unordered_results_come_as_they_finish() {
+        let (pool, gates, results) = pool(false, 4);
+        let mut finished = Vec::new();
+        for job in [3, 1, 2, 0] {
+            gates[job].send(()).unwrap();
+            finished.push(results.recv().unwrap());
+        }
+        pool.finish();
+        assert_eq!(
+            finished,
+            [
+                (3, Ok(30)),
+                (1, Err("job 1 failed".to_string())),
+                (2, Ok(20)),
+                (0, Ok(0))
+            ]
+        );
+    }
+}
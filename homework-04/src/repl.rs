@@ -0,0 +1,400 @@
+// Interactive mode: `<command> <input>` lines run on a pool of workers
+//
+// On a terminal the line is edited with rustyline. The history is kept in
+// ~/.homework-04_history, or in the file given by --history, and Ctrl-R
+// searches it. Tab completes the operation names, and the paths for csv and
+// the --file hashes. A line ending with `\` continues on the next one, the
+// input then has several lines. Lines starting with `:` are commands of the
+// REPL itself, see `:help`. Earlier results and variables can be used in
+// the input, see session.rs.
+//
+// When stdin is not a terminal the lines are read as they are, so commands
+// can still be piped in from a script.
+use crate::pool::WorkerPool;
+use crate::session::{is_continued, parse_line, Flow, Line, Session, META_COMMANDS};
+use homework_04::options::split_command;
+use homework_04::{Error, Options, Pipeline, Registry};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, ExternalPrinter, Helper};
+use std::env;
+use std::io::{self, BufRead, IsTerminal};
+use std::path::PathBuf;
+use std::thread;
+
+const HISTORY_FILE: &str = ".homework-04_history";
+const PROMPT: &str = "> ";
+
+// Completion, and the continuation of lines ending with `\`
+struct ReplHelper {
+    registry: Registry,
+    files: FilenameCompleter,
+}
+
+impl ReplHelper {
+    // Names starting with the word at `pos` and where the word starts, `None`
+    // when the word is the input of the command
+    fn candidates(&self, line: &str, pos: usize) -> Option<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        if before.starts_with(':') {
+            let names = META_COMMANDS.iter().filter(|name| name.starts_with(before));
+            return Some((0, names.map(|name| name.to_string()).collect()));
+        }
+        let (command, _) = split_command(line);
+        if pos > command.len() {
+            return None;
+        }
+        // The last stage of the pipeline, without options yet
+        let start = before.rfind('|').map_or(0, |i| i + 1);
+        let prefix = &before[start..];
+        if prefix.contains(':') {
+            return Some((pos, Vec::new()));
+        }
+        let names = self
+            .registry
+            .iter()
+            .flat_map(|t| std::iter::once(t.name()).chain(t.aliases().iter().copied()))
+            .filter(|name| name.starts_with(prefix))
+            .map(str::to_string)
+            .collect();
+        Some((start, names))
+    }
+
+    // The input of csv, or of a hash with the file flag, is a path
+    fn reads_path(&self, line: &str) -> bool {
+        let (command, _) = split_command(line);
+        self.registry
+            .parse_pipeline(command)
+            .ok()
+            .and_then(|pipeline| pipeline.stages().first().cloned())
+            .is_some_and(|stage| stage.transform.name() == "csv" || stage.options.flag("file"))
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        match self.candidates(line, pos) {
+            Some((start, names)) => Ok((
+                start,
+                names
+                    .into_iter()
+                    .map(|name| Pair {
+                        display: name.clone(),
+                        replacement: name,
+                    })
+                    .collect(),
+            )),
+            None if self.reads_path(line) => self.files.complete(line, pos, ctx),
+            None => Ok((pos, Vec::new())),
+        }
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if is_continued(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for ReplHelper {}
+
+// The lines are joined with newlines, the backslashes are dropped
+fn join_continued(entry: &str) -> String {
+    entry.replace("\\\n", "\n")
+}
+
+fn readline_error(e: ReadlineError) -> Error {
+    match e {
+        ReadlineError::Io(e) => Error::from(e),
+        e => Error::from(io::Error::other(e)),
+    }
+}
+
+// Where the lines come from
+enum Reader {
+    Editor {
+        editor: Box<Editor<ReplHelper, FileHistory>>,
+        history: Option<PathBuf>,
+    },
+    Plain {
+        lines: io::Lines<io::StdinLock<'static>>,
+        number: usize,
+    },
+}
+
+impl Reader {
+    fn new(registry: &Registry, options: &Options) -> Result<Reader, Error> {
+        if !io::stdin().is_terminal() {
+            return Ok(Reader::Plain {
+                lines: io::stdin().lock().lines(),
+                number: 0,
+            });
+        }
+        let mut editor = Editor::new().map_err(readline_error)?;
+        editor.set_helper(Some(ReplHelper {
+            registry: registry.clone(),
+            files: FilenameCompleter::new(),
+        }));
+        let history = match options.get("history") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE)),
+        };
+        if let Some(path) = &history {
+            // There is no history before the first run
+            let _ = editor.load_history(path);
+        }
+        Ok(Reader::Editor {
+            editor: Box::new(editor),
+            history,
+        })
+    }
+
+    // The next entry, `None` at the end of the input
+    fn read(&mut self) -> Option<Result<String, Error>> {
+        match self {
+            Reader::Editor { editor, .. } => match editor.readline(PROMPT) {
+                Ok(entry) => {
+                    if !entry.trim().is_empty() {
+                        let _ = editor.add_history_entry(entry.as_str());
+                    }
+                    Some(Ok(join_continued(&entry)))
+                }
+                Err(ReadlineError::Eof | ReadlineError::Interrupted) => None,
+                Err(e) => Some(Err(readline_error(e))),
+            },
+            Reader::Plain { lines, number } => {
+                let mut entry = String::new();
+                loop {
+                    *number += 1;
+                    match lines.next() {
+                        Some(Ok(line)) => entry.push_str(&line),
+                        Some(Err(e)) => {
+                            let message = format!("Line {}: {}", number, e);
+                            return Some(Err(Error::from(io::Error::new(e.kind(), message))));
+                        }
+                        // A continued last line is used as it is
+                        None if entry.is_empty() => return None,
+                        None => return Some(Ok(entry)),
+                    }
+                    if !is_continued(&entry) {
+                        return Some(Ok(entry));
+                    }
+                    entry.pop();
+                    entry.push('\n');
+                }
+            }
+        }
+    }
+
+    // Prints the results of the workers, on a terminal the prompt is drawn
+    // again below them
+    fn printer(&mut self) -> Printer {
+        match self {
+            Reader::Editor { editor, .. } if io::stdout().is_terminal() => {
+                match editor.create_external_printer() {
+                    Ok(printer) => Printer::Editor(Box::new(printer)),
+                    Err(_) => Printer::Stdout,
+                }
+            }
+            _ => Printer::Stdout,
+        }
+    }
+
+    fn clear(&mut self) {
+        if let Reader::Editor { editor, .. } = self {
+            let _ = editor.clear_screen();
+        }
+    }
+
+    fn save_history(&mut self) -> Result<(), Error> {
+        if let Reader::Editor {
+            editor,
+            history: Some(path),
+        } = self
+        {
+            editor
+                .save_history(path)
+                .map_err(|e| match readline_error(e) {
+                    Error::Io { source, .. } => Error::io(path.as_path(), source),
+                    e => e,
+                })?;
+        }
+        Ok(())
+    }
+}
+
+enum Printer {
+    Stdout,
+    Editor(Box<dyn ExternalPrinter + Send>),
+}
+
+impl Printer {
+    fn print(&mut self, text: &str, error: bool) {
+        match self {
+            Printer::Stdout if error => eprintln!("{}", text),
+            Printer::Stdout => println!("{}", text),
+            // The terminal may be in raw mode, where a newline doesn't return
+            Printer::Editor(printer) => {
+                let _ = printer.print(format!("{}\n", text).replace('\n', "\r\n"));
+            }
+        }
+    }
+}
+
+pub fn interactive(registry: Registry, options: Options) -> Result<(), Error> {
+    let default_jobs = thread::available_parallelism().map_or(1, |n| n.get());
+    let jobs = options.parse::<usize>("jobs")?.unwrap_or(default_jobs);
+    let queue = options.parse::<usize>("queue")?.unwrap_or(jobs * 2);
+    let ordered = !options.flag("unordered");
+
+    println!(
+        "Entrering interactive mode...\n\tExpected format: <command> <input>\n\tChain commands with '|': lowercase|no-spaces|reverse <input>\n\tCommand options: csv:select='name,age',where=age>30,sort=-age <path>\n\t                 replace:pattern='(\\d+) (\\w+)',with='$2 $1' <input>\n\tEnd a line with '\\' to continue the input on the next one\n\tUse earlier results in the input: $_ is the last one, $1 the first, :set name = value for $name\n\tAvailable commands: {}\n\tREPL commands: {}\nTo exit, press Ctrl+D or Ctrl+C\n",
+        registry.names().join(", "),
+        META_COMMANDS.join(", ")
+    );
+
+    let mut reader = Reader::new(&registry, &options)?;
+    let mut printer = reader.printer();
+    let mut session = Session::default();
+    let results = session.results.clone();
+    let mut pool = WorkerPool::new(
+        jobs,
+        queue,
+        ordered,
+        move |(pipeline, input): (Pipeline, String)| pipeline.filter_with(&input, &options),
+        move |sequence, result: Result<_, String>| {
+            let result = result.unwrap_or_else(|panic| {
+                Err(Error::transform(format!(
+                    "The operation panicked: {}",
+                    panic
+                )))
+            });
+            results.finish(sequence, &result);
+            // Without the order the line number tells which input the result is for
+            let prefix = if ordered {
+                String::new()
+            } else {
+                format!("[{}] ", sequence + 1)
+            };
+            match result {
+                Ok(Some(modified_input)) => {
+                    printer.print(&format!("{}{}", prefix, modified_input), false)
+                }
+                // Dropped by a filter like `matches`
+                Ok(None) => {}
+                Err(e) => printer.print(&format!("{}{}", prefix, e), true),
+            }
+        },
+    );
+
+    while let Some(entry) = reader.read() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        match parse_line(&entry) {
+            Ok(Line::Empty) => {}
+            Ok(Line::Meta(meta)) => {
+                match session.meta_command(meta, &registry, &mut io::stdout()) {
+                    Ok(Flow::Continue) => {}
+                    Ok(Flow::Clear) => reader.clear(),
+                    Ok(Flow::Quit) => break,
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+            Ok(Line::Command { command, input }) => {
+                // Waits for the results the input refers to
+                let input = match session.expand(input) {
+                    Ok(input) => input,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                };
+                match registry.parse_pipeline(command) {
+                    Ok(pipeline) => {
+                        session.results.add(&entry);
+                        pool.submit((pipeline, input));
+                    }
+                    Err(err) => eprintln!("Erro: {}", err),
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // End of input, wait for the lines which are still being processed
+    pool.finish();
+    reader.save_history()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn helper() -> ReplHelper {
+        ReplHelper {
+            registry: Registry::default(),
+            files: FilenameCompleter::new(),
+        }
+    }
+
+    #[test]
+    fn completes_commands_of_the_last_stage() {
+        let helper = helper();
+        let (start, names) = helper.candidates("title", 5).unwrap();
+        assert_eq!(
+            (start, names),
+            (0, vec!["title-case".to_string(), "title".to_string()])
+        );
+        let (start, names) = helper.candidates("lower|sn hello", 8).unwrap();
+        assert_eq!(start, 6);
+        assert_eq!(names, ["snake-case", "snake"]);
+        assert_eq!(helper.candidates(":c", 2).unwrap().1, [":clear"]);
+        assert!(helper.candidates("slugify:sep", 11).unwrap().1.is_empty());
+        assert_eq!(helper.candidates("upper hello", 9), None);
+    }
+
+    #[test]
+    fn paths_are_completed_for_files() {
+        let helper = helper();
+        assert!(helper.reads_path("csv data/"));
+        assert!(helper.reads_path("sha256:file ./"));
+        assert!(!helper.reads_path("sha256 text"));
+        assert!(!helper.reads_path("nope x"));
+    }
+
+    #[test]
+    fn continued_lines_are_joined() {
+        assert!(is_continued("sort-lines b\\"));
+        assert!(!is_continued("sort-lines b"));
+        assert_eq!(
+            join_continued("sort-lines b\\\na\\\nc"),
+            "sort-lines b\na\nc"
+        );
+    }
+}
@@ -0,0 +1,467 @@
+// JSON-RPC 2.0 over stdin and stdout, one message per line
+//
+// --> {"jsonrpc":"2.0","id":1,"method":"transform","params":{"operation":"slugify","input":"Hello World","options":{"separator":"_"}}}
+// <-- {"jsonrpc":"2.0","id":1,"result":{"output":"hello_world"}}
+//
+// Methods:
+//   listOperations                          name, aliases, description of the operations
+//   transform {operation, input, options}   `operation` is a pipeline like `normalize-whitespace|slugify:max-length=20`
+//   renderCsv {path | data, format, options}  `options` are the query options like `select` and `where`
+//
+// The requests run on the worker pool, the responses are written as soon as
+// they are ready, so they don't follow the order of the requests. Requests
+// without an `id` are notifications and get no response. A batch, an array
+// of requests, is run on one worker and answered with an array of the
+// responses, or not at all when it has only notifications. Failed operations
+// return an error with the kind and the exit code of the command line in
+// `data`, e.g. {"code":-32000,"message":"...","data":{"kind":"transform","exitCode":1}}.
+use crate::pool::WorkerPool;
+use homework_04::csv_operations::{parse_as_csv, render_csv};
+use homework_04::{Error, Options, Registry};
+use serde_json::{json, Map, Value};
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+// Errors of the operations, in the range reserved for the server
+const OPERATION_FAILED: i64 = -32000;
+
+struct Request {
+    // None for notifications
+    id: Option<Value>,
+    method: String,
+    params: Map<String, Value>,
+}
+
+enum Message {
+    Request(Request),
+    // The invalid requests are their error responses
+    Batch(Vec<Result<Request, Value>>),
+}
+
+enum Failure {
+    // The request is wrong, e.g. the method doesn't exist
+    Request { code: i64, message: String },
+    // The operation failed
+    Operation(Error),
+}
+
+impl Failure {
+    fn new(code: i64, message: impl Into<String>) -> Failure {
+        Failure::Request {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Failure {
+        Failure::new(
+            INVALID_PARAMS,
+            format!("Invalid params: {}", message.into()),
+        )
+    }
+
+    fn to_json(&self) -> Value {
+        let error = match self {
+            Failure::Request { code, message } => return json!({"code": code, "message": message}),
+            Failure::Operation(error) => error,
+        };
+        let mut data = json!({"kind": error.kind(), "exitCode": error.exit_code()});
+        // Where in the pipeline it failed
+        if let Error::Stage {
+            index, operation, ..
+        } = error
+        {
+            data["stage"] = json!(index);
+            data["operation"] = json!(operation);
+        }
+        if let Error::UnknownOperation { available, .. } = error.root() {
+            data["available"] = json!(available);
+        }
+        let code = match error.root() {
+            Error::InvalidArgument(_) => INVALID_PARAMS,
+            _ => OPERATION_FAILED,
+        };
+        json!({"code": code, "message": error.to_string(), "data": data})
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Failure {
+        Failure::Operation(error)
+    }
+}
+
+fn response(id: Value, result: Result<Value, Failure>) -> Value {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(e) => json!({"jsonrpc": "2.0", "id": id, "error": e.to_json()}),
+    }
+}
+
+// The error response when the line is not JSON or not a request
+fn parse_message(line: &str) -> Result<Message, Value> {
+    let message: Value = serde_json::from_str(line).map_err(|e| {
+        let error = Failure::new(PARSE_ERROR, format!("Parse error: {}", e));
+        response(Value::Null, Err(error))
+    })?;
+    match message {
+        Value::Array(messages) if messages.is_empty() => {
+            let error = Failure::new(INVALID_REQUEST, "Invalid request: empty batch");
+            Err(response(Value::Null, Err(error)))
+        }
+        Value::Array(messages) => Ok(Message::Batch(
+            messages.into_iter().map(parse_request).collect(),
+        )),
+        message => parse_request(message).map(Message::Request),
+    }
+}
+
+// The error response when the message is not a valid request
+fn parse_request(message: Value) -> Result<Request, Value> {
+    // The id is echoed only when it is valid
+    let id = match message.get("id") {
+        Some(id @ (Value::Null | Value::Number(_) | Value::String(_))) => Some(id.clone()),
+        None => None,
+        Some(_) => {
+            let error = Failure::new(
+                INVALID_REQUEST,
+                "Invalid request: id must be a number or a string",
+            );
+            return Err(response(Value::Null, Err(error)));
+        }
+    };
+    let invalid = |message: &str| {
+        let error = Failure::new(INVALID_REQUEST, format!("Invalid request: {}", message));
+        response(id.clone().unwrap_or(Value::Null), Err(error))
+    };
+    if !message.is_object() {
+        return Err(invalid("expected an object"));
+    }
+    if message.get("jsonrpc") != Some(&json!("2.0")) {
+        return Err(invalid("jsonrpc must be \"2.0\""));
+    }
+    let method = match message.get("method") {
+        Some(Value::String(method)) => method.clone(),
+        _ => return Err(invalid("method must be a string")),
+    };
+    let params = match message.get("params") {
+        None | Some(Value::Null) => Map::new(),
+        Some(Value::Object(params)) => params.clone(),
+        Some(_) => return Err(invalid("params must be an object")),
+    };
+    Ok(Request { id, method, params })
+}
+
+fn string_param<'a>(
+    params: &'a Map<String, Value>,
+    name: &str,
+) -> Result<Option<&'a str>, Failure> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(Failure::invalid_params(format!(
+            "{} must be a string",
+            name
+        ))),
+    }
+}
+
+fn required_param<'a>(params: &'a Map<String, Value>, name: &str) -> Result<&'a str, Failure> {
+    string_param(params, name)?.ok_or_else(|| Failure::invalid_params(format!("missing {}", name)))
+}
+
+// `{"select": "name,age", "head": 10, "stop-words": ["a", "the"]}`, arrays
+// repeat the option
+fn options_param(params: &Map<String, Value>) -> Result<Options, Failure> {
+    let invalid = |key: &str| {
+        Failure::invalid_params(format!(
+            "option {} must be a string, number or boolean",
+            key
+        ))
+    };
+    let mut options = Options::new();
+    let values = match params.get("options") {
+        None | Some(Value::Null) => return Ok(options),
+        Some(Value::Object(values)) => values,
+        Some(_) => return Err(Failure::invalid_params("options must be an object")),
+    };
+    for (key, value) in values {
+        let values = match value {
+            Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                Value::String(value) => options.push(key, value),
+                Value::Number(_) | Value::Bool(_) => options.push(key, &value.to_string()),
+                _ => return Err(invalid(key)),
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn list_operations(registry: &Registry) -> Value {
+    registry
+        .iter()
+        .map(|t| {
+            json!({
+                "name": t.name(),
+                "aliases": t.aliases(),
+                "description": t.description(),
+                "input": t.input_hint(),
+                "wholeInput": t.whole_input(),
+            })
+        })
+        .collect()
+}
+
+fn transform(
+    registry: &Registry,
+    options: &Options,
+    params: &Map<String, Value>,
+) -> Result<Value, Failure> {
+    let operation = required_param(params, "operation")?;
+    let input = required_param(params, "input")?;
+    let options = options.merged(&options_param(params)?);
+    let pipeline = registry.parse_pipeline(operation)?;
+    // null when the input is dropped by a filter like `matches`
+    let output = pipeline.filter_with(input, &options)?;
+    Ok(json!({ "output": output }))
+}
+
+fn render(options: &Options, params: &Map<String, Value>) -> Result<Value, Failure> {
+    let mut options = options.merged(&options_param(params)?);
+    if let Some(format) = string_param(params, "format")? {
+        options.set("output", format);
+    }
+    let output = match (string_param(params, "path")?, string_param(params, "data")?) {
+        (Some(path), None) => parse_as_csv(path, &options)?,
+        (None, Some(data)) => render_csv(data, &options)?,
+        _ => return Err(Failure::invalid_params("expected either path or data")),
+    };
+    Ok(json!({ "output": output }))
+}
+
+// Runs the method, None for notifications
+fn handle(registry: &Registry, options: &Options, request: Request) -> Option<Value> {
+    let result = match request.method.as_str() {
+        "listOperations" => Ok(list_operations(registry)),
+        "transform" => transform(registry, options, &request.params),
+        "renderCsv" => render(options, &request.params),
+        method => Err(Failure::new(
+            METHOD_NOT_FOUND,
+            format!("Method not found: {}", method),
+        )),
+    };
+    Some(response(request.id?, result))
+}
+
+// Runs `handle`, a panic is answered with an internal error. The pool would
+// only know the sequence of the job, the client needs the id.
+fn answer(registry: &Registry, options: &Options, request: Request) -> Option<Value> {
+    let id = request.id.clone();
+    panic::catch_unwind(AssertUnwindSafe(|| handle(registry, options, request))).unwrap_or_else(
+        |_| {
+            let error = Failure::new(INTERNAL_ERROR, "Internal error");
+            id.map(|id| response(id, Err(error)))
+        },
+    )
+}
+
+// None when there is nothing to answer
+fn answer_message(registry: &Registry, options: &Options, message: Message) -> Option<Value> {
+    match message {
+        Message::Request(request) => answer(registry, options, request),
+        Message::Batch(requests) => {
+            let responses: Vec<Value> = requests
+                .into_iter()
+                .filter_map(|request| match request {
+                    Ok(request) => answer(registry, options, request),
+                    Err(response) => Some(response),
+                })
+                .collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+    }
+}
+
+fn write_message(output: &Mutex<impl Write>, message: &Value) {
+    let mut output = output.lock().unwrap();
+    // Nobody is left to tell when the client is gone
+    let _ = writeln!(output, "{}", message).and_then(|_| output.flush());
+}
+
+pub fn serve<R, W>(registry: Registry, options: Options, input: R, output: W) -> Result<(), Error>
+where
+    R: BufRead,
+    W: Write + Send + 'static,
+{
+    let default_jobs = thread::available_parallelism().map_or(1, |n| n.get());
+    let jobs = options.parse::<usize>("jobs")?.unwrap_or(default_jobs);
+    let queue = options.parse::<usize>("queue")?.unwrap_or(jobs * 2);
+
+    let output = Arc::new(Mutex::new(output));
+    let sink_output = Arc::clone(&output);
+    let mut pool = WorkerPool::new(
+        jobs,
+        queue,
+        false,
+        move |message: Message| answer_message(&registry, &options, message),
+        // Panics are answered by `answer` already
+        move |_, response: Result<Option<Value>, String>| {
+            if let Ok(Some(response)) = response {
+                write_message(&sink_output, &response);
+            }
+        },
+    );
+
+    for line in input.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let error = Failure::new(PARSE_ERROR, format!("Parse error: {}", e));
+                write_message(&output, &response(Value::Null, Err(error)));
+                continue;
+            }
+            Err(e) => {
+                pool.finish();
+                return Err(e.into());
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_message(&line) {
+            Ok(message) => {
+                pool.submit(message);
+            }
+            Err(response) => write_message(&output, &response),
+        }
+    }
+
+    // End of input, wait for the requests which are still running
+    pool.finish();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn call(requests: &[&str]) -> Vec<Value> {
+        let output = Shared::default();
+        let input = requests.join("\n");
+        let options = Options::from_spec("jobs=2").unwrap();
+        serve(
+            Registry::default(),
+            options,
+            input.as_bytes(),
+            output.clone(),
+        )
+        .unwrap();
+        let output = output.0.lock().unwrap();
+        let mut responses: Vec<Value> = String::from_utf8_lossy(&output)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        responses.sort_by_key(|r| r["id"].as_i64());
+        responses
+    }
+
+    #[test]
+    fn operations_are_called_by_id() {
+        let responses = call(&[
+            r#"{"jsonrpc":"2.0","id":1,"method":"transform","params":{"operation":"normalize-whitespace|slugify","input":" Hello   World ","options":{"separator":"_"}}}"#,
+            r#"{"jsonrpc":"2.0","method":"transform","params":{"operation":"upper","input":"notification"}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"renderCsv","params":{"data":"name,age\nBob,4\nAlice,30\n","format":"csv","options":{"sort":"name"}}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"transform","params":{"operation":"matches:pattern=x","input":"abc"}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"listOperations"}"#,
+        ]);
+        assert_eq!(responses.len(), 4);
+        assert_eq!(
+            responses[0],
+            json!({"jsonrpc": "2.0", "id": 1, "result": {"output": "hello_world"}})
+        );
+        assert_eq!(
+            responses[1]["result"]["output"],
+            "name,age\nAlice,30\nBob,4\n"
+        );
+        assert_eq!(responses[2]["result"], json!({ "output": null }));
+        assert_eq!(responses[3]["result"][0]["name"], "lowercase");
+    }
+
+    #[test]
+    fn batches_get_an_array_of_responses() {
+        let responses = call(&[
+            r#"[{"jsonrpc":"2.0","id":1,"method":"transform","params":{"operation":"upper","input":"a"}},{"jsonrpc":"2.0","method":"transform","params":{"operation":"upper","input":"b"}},1,{"jsonrpc":"2.0","id":2,"method":"nope"}]"#,
+            r#"[{"jsonrpc":"2.0","method":"transform","params":{"operation":"upper","input":"c"}}]"#,
+        ]);
+        assert_eq!(
+            responses,
+            [json!([
+                {"jsonrpc": "2.0", "id": 1, "result": {"output": "A"}},
+                {"jsonrpc": "2.0", "id": null, "error": {"code": INVALID_REQUEST, "message": "Invalid request: expected an object"}},
+                {"jsonrpc": "2.0", "id": 2, "error": {"code": METHOD_NOT_FOUND, "message": "Method not found: nope"}},
+            ])]
+        );
+    }
+
+    #[test]
+    fn errors_carry_the_kind_and_exit_code() {
+        let responses = call(&[
+            "{not json",
+            "[]",
+            r#"{"jsonrpc":"2.0","id":1,"method":"reverse"}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"transform","params":{"input":"x"}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"transform","params":{"operation":"reverse|b64dec","input":"!"}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"transform","params":{"operation":"nope","input":"x"}}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"renderCsv","params":{"path":"/does/not/exist.csv"}}"#,
+            r#"{"jsonrpc":"2.0","id":6,"method":"renderCsv","params":{"data":"a\n1\n","format":"xml"}}"#,
+        ]);
+        let codes: Vec<(Value, i64)> = responses
+            .iter()
+            .map(|r| (r["id"].clone(), r["error"]["code"].as_i64().unwrap()))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                (Value::Null, PARSE_ERROR),
+                (Value::Null, INVALID_REQUEST),
+                (json!(1), METHOD_NOT_FOUND),
+                (json!(2), INVALID_PARAMS),
+                (json!(3), OPERATION_FAILED),
+                (json!(4), OPERATION_FAILED),
+                (json!(5), OPERATION_FAILED),
+                (json!(6), INVALID_PARAMS),
+            ]
+        );
+        assert_eq!(
+            responses[4]["error"]["data"],
+            json!({"kind": "transform", "exitCode": 1, "stage": 2, "operation": "base64-decode"})
+        );
+        assert_eq!(responses[5]["error"]["data"]["kind"], "unknown_operation");
+        assert_eq!(responses[6]["error"]["data"]["exitCode"], 5);
+        assert_eq!(responses[7]["error"]["data"]["kind"], "invalid_argument");
+    }
+}
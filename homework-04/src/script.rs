@@ -0,0 +1,265 @@
+// Script mode: the lines of a .ops file are run one after another
+//
+// homework-04 --script release.ops [--continue-on-error]
+//
+// A script has the lines typed in interactive mode, `<command> <input>` and
+// the REPL commands like `:set title = Hello`, see session.rs. The results
+// are printed in order and can be referred to as `$_`, `$1`, ... Lines
+// starting with `#` are comments, a line ending with `\` continues on the
+// next one and `include <path>` runs another script, the path is relative
+// to the script including it.
+//
+// The first failing line stops the script. With --continue-on-error the
+// rest is run and the failed lines are listed at the end, `file:line: error`.
+use crate::session::{is_continued, parse_line, Flow, Line, Session};
+use homework_04::options::split_command;
+use homework_04::{Error, Options, Registry};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// `lines` counts the lines which were run, commands, REPL commands and
+// includes, whether they failed or not, so `failures` is never more. Empty
+// lines and comments are not counted.
+#[derive(Debug, Default)]
+pub struct ScriptReport {
+    pub lines: usize,
+    pub failures: Vec<Error>,
+}
+
+struct Script<'a> {
+    registry: &'a Registry,
+    options: &'a Options,
+    out: &'a mut dyn Write,
+    session: Session,
+    continue_on_error: bool,
+    report: ScriptReport,
+    // Scripts being run, for finding include cycles
+    running: Vec<PathBuf>,
+}
+
+// `(line number, entry)` with the continued lines joined, the number is the
+// one of the first line
+fn entries(text: &str) -> Vec<(usize, String)> {
+    let mut entries = Vec::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let mut entry = line.to_string();
+        while is_continued(&entry) {
+            entry.pop();
+            match lines.next() {
+                Some((_, next)) => {
+                    entry.push('\n');
+                    entry.push_str(next);
+                }
+                None => break,
+            }
+        }
+        entries.push((i + 1, entry));
+    }
+    entries
+}
+
+impl Script<'_> {
+    fn run_file(&mut self, path: &Path) -> Result<Flow, Error> {
+        let canonical = fs::canonicalize(path).map_err(|e| Error::io(path, e))?;
+        if self.running.contains(&canonical) {
+            return Err(Error::invalid_argument(format!(
+                "{} includes itself",
+                path.display()
+            )));
+        }
+        let text = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        self.running.push(canonical);
+        let flow = self.run_entries(path, &text);
+        self.running.pop();
+        flow
+    }
+
+    fn run_entries(&mut self, path: &Path, text: &str) -> Result<Flow, Error> {
+        for (number, entry) in entries(text) {
+            if matches!(parse_line(&entry), Ok(Line::Empty)) {
+                continue;
+            }
+            self.report.lines += 1;
+            let result = match split_command(entry.trim()) {
+                ("include", "") => Err(Error::invalid_argument("include needs a path")),
+                ("include", include) => {
+                    let directory = path.parent().unwrap_or(Path::new(""));
+                    match self.run_file(&directory.join(include.trim())) {
+                        // The failed line of the included script is already named
+                        Err(e @ Error::Script { .. }) => return Err(e),
+                        result => result,
+                    }
+                }
+                _ => self.run_entry(&entry),
+            };
+            match result {
+                Ok(Flow::Quit) => return Ok(Flow::Quit),
+                Ok(_) => {}
+                Err(e) => {
+                    let error = Error::Script {
+                        path: path.to_path_buf(),
+                        line: number,
+                        source: Box::new(e),
+                    };
+                    if !self.continue_on_error {
+                        return Err(error);
+                    }
+                    self.report.failures.push(error);
+                }
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn run_entry(&mut self, entry: &str) -> Result<Flow, Error> {
+        match parse_line(entry)? {
+            Line::Empty => Ok(Flow::Continue),
+            Line::Meta(meta) => self.session.meta_command(meta, self.registry, self.out),
+            Line::Command { command, input } => {
+                let input = self.session.expand(input)?;
+                let pipeline = self.registry.parse_pipeline(command)?;
+                let sequence = self.session.results.add(entry);
+                let result = pipeline.filter_with(&input, self.options);
+                self.session.results.finish(sequence, &result);
+                if let Some(output) = result? {
+                    writeln!(self.out, "{}", output)?;
+                }
+                Ok(Flow::Continue)
+            }
+        }
+    }
+}
+
+// The results are written into `out`
+pub fn run_script(
+    path: &str,
+    registry: &Registry,
+    options: &Options,
+    out: &mut dyn Write,
+) -> Result<ScriptReport, Error> {
+    let mut script = Script {
+        registry,
+        options,
+        out,
+        session: Session::default(),
+        continue_on_error: options.flag("continue-on-error"),
+        report: ScriptReport::default(),
+        running: Vec::new(),
+    };
+    script.run_file(Path::new(path))?;
+    Ok(script.report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(directory: &Path, name: &str, text: &str) -> PathBuf {
+        let path = directory.join(name);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("homework-04-script-{}", name));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn continued_lines_are_one_entry() {
+        assert_eq!(
+            entries("# title\nsort b\\\na\\\nc\nupper x\n"),
+            [
+                (1, "# title".to_string()),
+                (2, "sort b\na\nc".to_string()),
+                (5, "upper x".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_included_scripts_with_variables() {
+        let directory = directory("include");
+        write(
+            &directory,
+            "common.ops",
+            ":set name = World\nlower FROM COMMON\n",
+        );
+        let path = write(
+            &directory,
+            "main.ops",
+            "# greeting\ninclude\tcommon.ops\n:set\nupper hello $name\nreverse $_\n:quit\nb64dec !\n",
+        );
+        let options = Options::new();
+        let mut out = Vec::new();
+        let report = run_script(
+            path.to_str().unwrap(),
+            &Registry::default(),
+            &options,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "from common\nname = World\nHELLO WORLD\nDLROW OLLEH\n"
+        );
+        // The lines up to :quit, with the include line and the two of common.ops
+        assert_eq!(report.lines, 7);
+        assert!(report.failures.is_empty());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn stops_at_the_first_failure() {
+        let directory = directory("failure");
+        write(&directory, "inner.ops", "upper ok\nb64dec !\n");
+        let path = write(
+            &directory,
+            "main.ops",
+            "upper a\ninclude inner.ops\nnope x\ninclude missing.ops\ninclude main.ops\n",
+        );
+        let registry = Registry::default();
+
+        let error = run_script(
+            path.to_str().unwrap(),
+            &registry,
+            &Options::new(),
+            &mut Vec::new(),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), "transform");
+        assert!(
+            matches!(error, Error::Script { ref path, line: 2, .. } if path.ends_with("inner.ops"))
+        );
+
+        let options = Options::from_spec("continue-on-error").unwrap();
+        let report =
+            run_script(path.to_str().unwrap(), &registry, &options, &mut Vec::new()).unwrap();
+        let failures: Vec<(String, &str)> = report
+            .failures
+            .iter()
+            .map(|e| match e {
+                Error::Script { path, line, .. } => (
+                    format!("{}:{}", path.file_name().unwrap().to_str().unwrap(), line),
+                    e.kind(),
+                ),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            failures,
+            [
+                ("inner.ops:2".to_string(), "transform"),
+                ("main.ops:3".to_string(), "unknown_operation"),
+                ("main.ops:4".to_string(), "io"),
+                ("main.ops:5".to_string(), "invalid_argument"),
+            ]
+        );
+        // The four failed lines and `upper a`, `include inner.ops`, `upper ok`
+        assert_eq!(report.lines, 7);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,413 @@
+// Results and variables of an interactive session
+//
+// Every command gets a number when it is submitted, `$3` is the result of
+// the third command and `$_` the result of the last one. `:set name = value`
+// sets a variable used as `$name`, or `${name}` when a letter follows it,
+// and `$$` is a dollar sign. The commands run on the workers in the
+// background, a reference to a result waits until it is ready.
+//
+// > upper hello world
+// HELLO WORLD
+// > :set who = $_
+// > reverse $who!
+// !DLROW OLLEH
+//
+// The lines are parsed the same way in the REPL and in scripts.
+use homework_04::options::split_command;
+use homework_04::{Error, Registry};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::{Arc, Condvar, Mutex};
+
+pub const META_COMMANDS: [&str; 6] = [":help", ":ops", ":set", ":history", ":clear", ":quit"];
+
+// What a line asks for
+#[derive(Debug, PartialEq)]
+pub enum Line<'a> {
+    // Blank lines and `#` comments
+    Empty,
+    // `:set title = Hello`
+    Meta(&'a str),
+    // `slugify Hello World`, the input is the rest of the line as it is
+    Command { command: &'a str, input: &'a str },
+}
+
+pub fn parse_line(entry: &str) -> Result<Line<'_>, Error> {
+    let trimmed = entry.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return Ok(Line::Empty);
+    }
+    if trimmed.starts_with(':') {
+        return Ok(Line::Meta(trimmed));
+    }
+    // Handle the rest of the input as one string, quoted option values
+    // may contain spaces
+    // slugify Hello World --> hello-world
+    let (command, input) = split_command(entry);
+    if command.is_empty() {
+        return Err(Error::invalid_argument(
+            "Invalid input format. Expected: <command> <input>",
+        ));
+    }
+    if input.trim().is_empty() {
+        return Err(Error::invalid_argument(
+            "Input for command is missing. Expected: <command> <input>",
+        ));
+    }
+    Ok(Line::Command { command, input })
+}
+
+// A line ending with `\` continues on the next one
+pub fn is_continued(line: &str) -> bool {
+    line.ends_with('\\')
+}
+
+pub enum Flow {
+    Continue,
+    Clear,
+    Quit,
+}
+
+fn print_help(out: &mut dyn Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "<command> <input>          run the command, e.g. title-case hello world
+<command>|<command> <input>  chain commands
+<command> <input> \\         continue the input on the next line
+$_, $1, $2, ...            the last, first, second, ... result in the input
+$name, ${{name}}             a variable in the input, $$ is a dollar sign
+:set <name> = <value>      set a variable, :set alone lists them
+:history                   list the numbered results
+:ops                       list the commands
+:clear                     clear the screen
+:quit                      exit, also Ctrl+D
+# comment                  ignored
+
+Tab completes the commands and the paths for csv, Ctrl+R searches the history."
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    Pending,
+    Done(String),
+    // Dropped by a filter like `matches`
+    Dropped,
+    Failed(String),
+}
+
+#[derive(Debug)]
+struct Entry {
+    command: String,
+    outcome: Outcome,
+}
+
+// Results of the submitted commands, shared with the thread printing them
+#[derive(Clone, Default)]
+pub struct Results {
+    shared: Arc<(Mutex<Vec<Entry>>, Condvar)>,
+}
+
+impl Results {
+    // Adds a pending result, its index is the sequence number of the job
+    pub fn add(&self, command: &str) -> u64 {
+        let mut entries = self.shared.0.lock().unwrap();
+        entries.push(Entry {
+            command: command.to_string(),
+            outcome: Outcome::Pending,
+        });
+        entries.len() as u64 - 1
+    }
+
+    pub fn finish(&self, sequence: u64, result: &Result<Option<String>, Error>) {
+        let (entries, ready) = &*self.shared;
+        let mut entries = entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(sequence as usize) {
+            entry.outcome = match result {
+                Ok(Some(output)) => Outcome::Done(output.clone()),
+                Ok(None) => Outcome::Dropped,
+                Err(e) => Outcome::Failed(e.to_string()),
+            };
+        }
+        ready.notify_all();
+    }
+
+    // The result of the 1-based `number`, waits while it is running
+    fn get(&self, number: usize) -> Result<String, Error> {
+        let (entries, ready) = &*self.shared;
+        let mut entries = entries.lock().unwrap();
+        if number == 0 || number > entries.len() {
+            return Err(Error::invalid_argument(format!(
+                "There is no result ${}",
+                number
+            )));
+        }
+        while entries[number - 1].outcome == Outcome::Pending {
+            entries = ready.wait(entries).unwrap();
+        }
+        match &entries[number - 1].outcome {
+            Outcome::Done(output) => Ok(output.clone()),
+            Outcome::Dropped => Err(Error::invalid_argument(format!(
+                "${} was dropped by a filter",
+                number
+            ))),
+            Outcome::Failed(e) => Err(Error::invalid_argument(format!(
+                "${} failed: {}",
+                number, e
+            ))),
+            Outcome::Pending => unreachable!("waited for the result"),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.shared.0.lock().unwrap().len()
+    }
+
+    // The numbered commands with their results indented below them
+    pub fn history(&self) -> String {
+        let entries = self.shared.0.lock().unwrap();
+        let mut history = String::new();
+        for (i, entry) in entries.iter().enumerate() {
+            history.push_str(&format!("${} {}\n", i + 1, entry.command));
+            let outcome = match &entry.outcome {
+                Outcome::Pending => "(running)".to_string(),
+                Outcome::Done(output) => output.clone(),
+                Outcome::Dropped => "(dropped)".to_string(),
+                Outcome::Failed(e) => format!("(failed) {}", e),
+            };
+            for line in outcome.lines() {
+                history.push_str(&format!("    {}\n", line));
+            }
+        }
+        history
+    }
+}
+
+#[derive(Default)]
+pub struct Session {
+    pub results: Results,
+    variables: BTreeMap<String, String>,
+}
+
+fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Session {
+    // `name = value`, the references in the value are expanded first
+    pub fn set(&mut self, assignment: &str) -> Result<(), Error> {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| Error::invalid_argument("Expected :set <name> = <value>"))?;
+        let name = name.trim();
+        if !is_name(name) {
+            return Err(Error::invalid_argument(format!(
+                "Invalid variable name '{}', use letters, digits and _",
+                name
+            )));
+        }
+        let value = self.expand(value.trim())?;
+        self.variables.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    // Runs a REPL command, `:help`, `:set title = Hello`, ..., the listings
+    // are written into `out`
+    pub fn meta_command(
+        &mut self,
+        entry: &str,
+        registry: &Registry,
+        out: &mut dyn Write,
+    ) -> Result<Flow, Error> {
+        let (name, arguments) = split_command(entry.trim());
+        match name {
+            ":help" => print_help(out)?,
+            ":ops" => write!(out, "{}", registry.help())?,
+            ":set" if arguments.trim().is_empty() => write!(out, "{}", self.variables())?,
+            ":set" => self.set(arguments)?,
+            ":history" => write!(out, "{}", self.results.history())?,
+            ":clear" => return Ok(Flow::Clear),
+            ":quit" => return Ok(Flow::Quit),
+            _ => {
+                return Err(Error::invalid_argument(format!(
+                    "Unknown command '{}'. Available: {}",
+                    name,
+                    META_COMMANDS.join(", ")
+                )))
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    // `name = value` lines, sorted by the name
+    pub fn variables(&self) -> String {
+        self.variables
+            .iter()
+            .map(|(name, value)| format!("{} = {}\n", name, value))
+            .collect()
+    }
+
+    fn resolve(&self, reference: &str) -> Result<String, Error> {
+        if reference == "_" {
+            return match self.results.len() {
+                0 => Err(Error::invalid_argument("There is no previous result")),
+                last => self.results.get(last),
+            };
+        }
+        if let Ok(number) = reference.parse() {
+            return self.results.get(number);
+        }
+        self.variables
+            .get(reference)
+            .cloned()
+            .ok_or_else(|| Error::invalid_argument(format!("Unknown variable ${}", reference)))
+    }
+
+    // Replaces the references to results and variables in `input`
+    pub fn expand(&self, input: &str) -> Result<String, Error> {
+        let mut expanded = String::with_capacity(input.len());
+        let mut rest = input;
+        while let Some(dollar) = rest.find('$') {
+            expanded.push_str(&rest[..dollar]);
+            rest = &rest[dollar + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                expanded.push('$');
+                rest = after;
+                continue;
+            }
+            let (reference, length) = if let Some(braced) = rest.strip_prefix('{') {
+                let end = braced
+                    .find('}')
+                    .ok_or_else(|| Error::invalid_argument("Missing } after ${"))?;
+                (&braced[..end], end + 2)
+            } else {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                // `$5` followed by letters is the result 5
+                let digits = rest[..end].len()
+                    - rest[..end]
+                        .trim_start_matches(|c: char| c.is_ascii_digit())
+                        .len();
+                let end = if digits > 0 { digits } else { end };
+                (&rest[..end], end)
+            };
+            if reference.is_empty() {
+                // A lone dollar sign
+                expanded.push('$');
+                continue;
+            }
+            expanded.push_str(&self.resolve(reference)?);
+            rest = &rest[length..];
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    fn session(results: &[&str]) -> Session {
+        let session = Session::default();
+        for result in results {
+            let sequence = session.results.add("upper");
+            session
+                .results
+                .finish(sequence, &Ok(Some(result.to_string())));
+        }
+        session
+    }
+
+    #[test]
+    fn results_and_variables_are_expanded() {
+        let mut session = session(&["HELLO", "WORLD"]);
+        assert_eq!(session.expand("$1 $2!").unwrap(), "HELLO WORLD!");
+        assert_eq!(
+            session.expand("$_, $2x, $$5, $").unwrap(),
+            "WORLD, WORLDx, $5, $"
+        );
+        session.set("title = $1 there").unwrap();
+        session.set("n=2").unwrap();
+        assert_eq!(
+            session.expand("[$title] ${n}nd").unwrap(),
+            "[HELLO there] 2nd"
+        );
+        assert_eq!(session.variables(), "n = 2\ntitle = HELLO there\n");
+
+        assert!(session.expand("$3").is_err());
+        assert!(session.expand("$nope").is_err());
+        assert!(session.expand("${title").is_err());
+        assert!(session.set("2x = y").is_err());
+        assert!(session.set("title").is_err());
+        assert!(Session::default().expand("$_").is_err());
+    }
+
+    #[test]
+    fn lines_are_commands_or_meta_commands() {
+        assert_eq!(parse_line("  # note").unwrap(), Line::Empty);
+        assert_eq!(parse_line("").unwrap(), Line::Empty);
+        assert_eq!(parse_line(" :set a = b").unwrap(), Line::Meta(":set a = b"));
+        assert_eq!(
+            parse_line("replace:with='a b' x  y").unwrap(),
+            Line::Command {
+                command: "replace:with='a b'",
+                input: "x  y"
+            }
+        );
+        assert!(parse_line("upper").is_err());
+        assert!(parse_line(" upper x").is_err());
+
+        let mut session = Session::default();
+        let registry = Registry::default();
+        let mut out = Vec::new();
+        assert!(matches!(
+            session.meta_command(":set x = 1", &registry, &mut out),
+            Ok(Flow::Continue)
+        ));
+        assert!(matches!(
+            session.meta_command(":quit", &registry, &mut out),
+            Ok(Flow::Quit)
+        ));
+        assert!(session.meta_command(":nope", &registry, &mut out).is_err());
+        session.meta_command(":set", &registry, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "x = 1\n");
+    }
+
+    #[test]
+    fn references_wait_for_the_result() {
+        let session = Session::default();
+        let sequence = session.results.add("slow");
+        let results = session.results.clone();
+        let worker = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            results.finish(sequence, &Ok(Some("done".to_string())));
+        });
+        assert_eq!(session.expand("$_").unwrap(), "done");
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn history_lists_the_outcomes() {
+        let session = session(&["A\nB"]);
+        let sequence = session.results.add("matches:pattern=x");
+        session.results.finish(sequence, &Ok(None));
+        let sequence = session.results.add("b64dec !");
+        session
+            .results
+            .finish(sequence, &Err(Error::transform("Invalid base64")));
+        session.results.add("slow");
+        assert_eq!(
+            session.results.history(),
+            "$1 upper\n    A\n    B\n$2 matches:pattern=x\n    (dropped)\n$3 b64dec !\n    (failed) Invalid base64\n$4 slow\n    (running)\n"
+        );
+        assert_eq!(
+            session.expand("$2").unwrap_err().to_string(),
+            "$2 was dropped by a filter"
+        );
+    }
+}
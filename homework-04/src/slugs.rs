@@ -0,0 +1,233 @@
+//! URL slugs with a configurable separator, length and word list.
+//!
+//! The text is transliterated to ASCII, `Žluťoučký kůň` --> `zlutoucky-kun`,
+//! and split into words on everything which is not a letter or a digit.
+//! Stop words are left out, the rest is joined with the separator.
+
+use crate::error::Error;
+use crate::options::Options;
+use deunicode::deunicode_char;
+use std::collections::HashSet;
+
+/// Separators accepted by the `separator` option.
+pub const SEPARATORS: [&str; 3] = ["-", "_", "."];
+
+/// How [`slugify_with`] builds the slug.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlugOptions {
+    pub separator: String,
+    /// The slug is cut after the last whole word which fits.
+    pub max_length: Option<usize>,
+    /// Words left out, unless the slug would be empty without them.
+    pub stop_words: Vec<String>,
+    /// Replacements done before the transliteration, e.g. `&` --> `and`.
+    pub translit: Vec<(String, String)>,
+}
+
+impl Default for SlugOptions {
+    fn default() -> Self {
+        SlugOptions {
+            separator: "-".to_string(),
+            max_length: None,
+            stop_words: Vec::new(),
+            translit: Vec::new(),
+        }
+    }
+}
+
+impl SlugOptions {
+    /// Reads the `separator`, `max-length`, `stop-words` and `translit`
+    /// options.
+    ///
+    /// `stop-words` and `translit` are comma separated lists and can be
+    /// repeated, `translit` entries are `from=to`:
+    ///
+    /// ```text
+    /// slugify:separator=_,max-length=40,stop-words='a,the',translit='&=and,ß=ss'
+    /// ```
+    pub fn from_options(options: &Options) -> Result<SlugOptions, Error> {
+        let separator = options.get("separator").unwrap_or("-");
+        if !SEPARATORS.contains(&separator) {
+            return Err(Error::invalid_argument(format!(
+                "Invalid separator: '{}'\nAvailable separators: {}",
+                separator,
+                SEPARATORS.join(" ")
+            )));
+        }
+        let translit = options
+            .get_list("translit")
+            .into_iter()
+            .map(|entry| match entry.split_once('=') {
+                Some((from, to)) if !from.is_empty() => Ok((from.to_string(), to.to_string())),
+                _ => Err(Error::invalid_argument(format!(
+                    "Invalid transliteration '{}', expected <from>=<to>",
+                    entry
+                ))),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(SlugOptions {
+            separator: separator.to_string(),
+            max_length: options.parse("max-length")?,
+            stop_words: options
+                .get_list("stop-words")
+                .into_iter()
+                .map(str::to_lowercase)
+                .collect(),
+            translit,
+        })
+    }
+}
+
+/// `Tom & Jerry: The Movie` --> `tom-jerry-the-movie`
+pub fn slugify_with(input: &str, options: &SlugOptions) -> String {
+    let ascii = transliterate(input, &options.translit);
+    let words: Vec<String> = ascii
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    let kept: Vec<&String> = words
+        .iter()
+        .filter(|word| !options.stop_words.contains(word))
+        .collect();
+    let kept = if kept.is_empty() {
+        words.iter().collect()
+    } else {
+        kept
+    };
+
+    let mut slug = String::new();
+    for word in kept {
+        let length = if slug.is_empty() {
+            word.len()
+        } else {
+            slug.len() + options.separator.len() + word.len()
+        };
+        if options.max_length.is_some_and(|max| length > max) {
+            // A single word longer than the limit is cut
+            if slug.is_empty() {
+                slug.push_str(&word[..options.max_length.unwrap_or(0)]);
+            }
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push_str(&options.separator);
+        }
+        slug.push_str(word);
+    }
+    slug
+}
+
+// Applies the overrides, longest match first, and then deunicode
+fn transliterate(input: &str, translit: &[(String, String)]) -> String {
+    let mut overrides: Vec<&(String, String)> = translit.iter().collect();
+    overrides.sort_by_key(|(from, _)| std::cmp::Reverse(from.len()));
+
+    let mut ascii = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        if let Some((from, to)) = overrides.iter().find(|(from, _)| rest.starts_with(from)) {
+            ascii.push_str(to);
+            rest = &rest[from.len()..];
+            continue;
+        }
+        if c.is_ascii() {
+            ascii.push(c);
+        } else {
+            // Unknown characters separate words
+            ascii.push_str(deunicode_char(c).unwrap_or(" "));
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    ascii
+}
+
+/// Makes the slugs of a batch unique by adding `-2`, `-3`, ... to the
+/// repeated ones.
+#[derive(Debug, Default)]
+pub struct Uniquifier {
+    separator: String,
+    seen: HashSet<String>,
+}
+
+impl Uniquifier {
+    pub fn new(separator: &str) -> Uniquifier {
+        Uniquifier {
+            separator: separator.to_string(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// `slug` when it wasn't seen yet, otherwise the first free `slug-<n>`.
+    pub fn unique(&mut self, slug: &str) -> String {
+        let mut unique = slug.to_string();
+        let mut n = 2;
+        while self.seen.contains(&unique) {
+            unique = format!("{}{}{}", slug, self.separator, n);
+            n += 1;
+        }
+        self.seen.insert(unique.clone());
+        unique
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slug(input: &str, spec: &str) -> String {
+        let options = SlugOptions::from_options(&Options::from_spec(spec).unwrap()).unwrap();
+        slugify_with(input, &options)
+    }
+
+    #[test]
+    fn same_as_the_slug_crate_by_default() {
+        for input in [
+            "My Test String!!!1!1",
+            "  --test_-_cool",
+            "Æúű--cool?",
+            "You & Me",
+            "Žluťoučký kůň úpěl",
+            "東京",
+            "",
+        ] {
+            assert_eq!(slug(input, ""), slug::slugify(input), "{}", input);
+        }
+    }
+
+    #[test]
+    fn separator_and_max_length() {
+        assert_eq!(slug("Hello big World", "separator=_"), "hello_big_world");
+        assert_eq!(slug("Hello big World", "separator=."), "hello.big.world");
+        assert_eq!(slug("Hello big World", "max-length=10"), "hello-big");
+        assert_eq!(slug("Hello big World", "max-length=9"), "hello-big");
+        assert_eq!(slug("Hello big World", "max-length=8"), "hello");
+        assert_eq!(slug("Supercalifragilistic", "max-length=5"), "super");
+        assert!(SlugOptions::from_options(&Options::from_spec("separator=+").unwrap()).is_err());
+    }
+
+    #[test]
+    fn stop_words_and_transliteration() {
+        assert_eq!(
+            slug("The Lord of the Rings", "stop-words='the,of'"),
+            "lord-rings"
+        );
+        assert_eq!(slug("The Of", "stop-words='the,of'"), "the-of");
+        assert_eq!(
+            slug("Tom & Jerry in der Straße", "translit='&=and,ß=ss'"),
+            "tom-and-jerry-in-der-strasse"
+        );
+        assert_eq!(slug("C++ & C#", "translit='++=pp,#=sharp'"), "cpp-csharp");
+        assert!(SlugOptions::from_options(&Options::from_spec("translit=x").unwrap()).is_err());
+    }
+
+    #[test]
+    fn repeated_slugs_get_a_number() {
+        let mut uniquifier = Uniquifier::new("-");
+        assert_eq!(uniquifier.unique("hello"), "hello");
+        assert_eq!(uniquifier.unique("hello"), "hello-2");
+        assert_eq!(uniquifier.unique("hello-2"), "hello-2-2");
+        assert_eq!(uniquifier.unique("hello"), "hello-3");
+    }
+}
@@ -0,0 +1,299 @@
+//! Statistics of a text for checking content before it is published.
+//!
+//! Words and sentences are found with the Unicode segmentation rules, so the
+//! counts work for any language. The readability scores are the English
+//! Flesch reading ease and Flesch–Kincaid grade level, syllables are counted
+//! as groups of vowels.
+
+use crate::csv_operations::CsvRecords;
+use crate::error::Error;
+use crate::options::Options;
+use csv::StringRecord;
+use deunicode::deunicode;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Number of most frequent words reported without the `top` option.
+pub const DEFAULT_TOP: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStats {
+    /// Grapheme clusters, so `é` is one character however it is written.
+    pub characters: usize,
+    pub words: usize,
+    pub sentences: usize,
+    pub lines: usize,
+    /// Blocks of lines separated by empty lines.
+    pub paragraphs: usize,
+    pub syllables: usize,
+    /// Characters per word.
+    pub average_word_length: f64,
+    /// Most frequent lowercase words with their counts, the most frequent first.
+    pub top_words: Vec<(String, usize)>,
+}
+
+impl TextStats {
+    /// Statistics of `input`, `stop_words` are left out of the top words.
+    pub fn of(input: &str, stop_words: &[String], top: usize) -> TextStats {
+        let words: Vec<&str> = input.unicode_words().collect();
+        let word_characters: usize = words.iter().map(|word| word.graphemes(true).count()).sum();
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in &words {
+            let word = word.to_lowercase();
+            if !stop_words.contains(&word) {
+                *counts.entry(word).or_default() += 1;
+            }
+        }
+        let mut top_words: Vec<(String, usize)> = counts.into_iter().collect();
+        top_words.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        top_words.truncate(top);
+
+        TextStats {
+            characters: input.graphemes(true).count(),
+            words: words.len(),
+            sentences: sentences(input),
+            lines: input.lines().count(),
+            paragraphs: paragraphs(input),
+            syllables: words.iter().map(|word| syllables(word)).sum(),
+            average_word_length: ratio(word_characters, words.len()),
+            top_words,
+        }
+    }
+
+    /// 206.835 − 1.015 × words per sentence − 84.6 × syllables per word,
+    /// higher is easier. `None` for a text without words.
+    pub fn flesch_reading_ease(&self) -> Option<f64> {
+        self.readability(|per_sentence, per_word| 206.835 - 1.015 * per_sentence - 84.6 * per_word)
+    }
+
+    /// 0.39 × words per sentence + 11.8 × syllables per word − 15.59, the
+    /// US school grade the text is written for.
+    pub fn flesch_kincaid_grade(&self) -> Option<f64> {
+        self.readability(|per_sentence, per_word| 0.39 * per_sentence + 11.8 * per_word - 15.59)
+    }
+
+    fn readability(&self, score: fn(f64, f64) -> f64) -> Option<f64> {
+        if self.words == 0 {
+            return None;
+        }
+        // A text without sentence punctuation is one sentence
+        let sentences = self.sentences.max(1);
+        Some(score(
+            ratio(self.words, sentences),
+            ratio(self.syllables, self.words),
+        ))
+    }
+
+    /// Table with a row for every statistic, followed by the top words.
+    pub fn to_table(&self) -> String {
+        let rounded = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.2}", v));
+        let rows = [
+            ("characters", self.characters.to_string()),
+            ("words", self.words.to_string()),
+            ("sentences", self.sentences.to_string()),
+            ("lines", self.lines.to_string()),
+            ("paragraphs", self.paragraphs.to_string()),
+            (
+                "average word length",
+                rounded(Some(self.average_word_length)),
+            ),
+            ("flesch reading ease", rounded(self.flesch_reading_ease())),
+            ("flesch-kincaid grade", rounded(self.flesch_kincaid_grade())),
+        ];
+        let summary = CsvRecords {
+            headers: StringRecord::from(vec!["statistic", "value"]),
+            records: rows
+                .iter()
+                .map(|(name, value)| StringRecord::from(vec![*name, value.as_str()]))
+                .collect(),
+        };
+        if self.top_words.is_empty() {
+            return summary.to_string();
+        }
+        let top_words = CsvRecords {
+            headers: StringRecord::from(vec!["word", "count"]),
+            records: self
+                .top_words
+                .iter()
+                .map(|(word, count)| StringRecord::from(vec![word.clone(), count.to_string()]))
+                .collect(),
+        };
+        format!("{}\n{}", summary, top_words)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let rounded = |value: f64| (value * 100.0).round() / 100.0;
+        json!({
+            "characters": self.characters,
+            "words": self.words,
+            "sentences": self.sentences,
+            "lines": self.lines,
+            "paragraphs": self.paragraphs,
+            "average_word_length": rounded(self.average_word_length),
+            "flesch_reading_ease": self.flesch_reading_ease().map(rounded),
+            "flesch_kincaid_grade": self.flesch_kincaid_grade().map(rounded),
+            "top_words": self
+                .top_words
+                .iter()
+                .map(|(word, count)| json!({ "word": word, "count": count }))
+                .collect::<Vec<Value>>(),
+        })
+    }
+}
+
+/// Statistics of the input as a table, or JSON with `output=json`.
+///
+/// `top` sets the number of most frequent words, `stop-words` is a comma
+/// separated list of words left out of them.
+pub fn stats(input: &str, options: &Options) -> Result<String, Error> {
+    let stop_words: Vec<String> = options
+        .get_list("stop-words")
+        .into_iter()
+        .map(str::to_lowercase)
+        .collect();
+    let top = options.parse("top")?.unwrap_or(DEFAULT_TOP);
+    let stats = TextStats::of(input, &stop_words, top);
+
+    match options.get("output").unwrap_or("table") {
+        "table" => Ok(stats.to_table().trim_end().to_string()),
+        "json" => serde_json::to_string_pretty(&stats.to_json())
+            .map_err(|e| Error::transform(e.to_string())),
+        format => Err(Error::invalid_argument(format!(
+            "Unknown output format: {}\nAvailable formats: table, json",
+            format
+        ))),
+    }
+}
+
+// Sentences with at least one word
+fn sentences(input: &str) -> usize {
+    if input.is_empty() {
+        return 0;
+    }
+    input
+        .unicode_sentences()
+        .filter(|sentence| sentence.unicode_words().next().is_some())
+        .count()
+}
+
+// Groups of lines separated by blank lines
+fn paragraphs(input: &str) -> usize {
+    let mut count = 0;
+    let mut blank = true;
+    for line in input.lines() {
+        let is_blank = line.trim().is_empty();
+        if blank && !is_blank {
+            count += 1;
+        }
+        blank = is_blank;
+    }
+    count
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+// Groups of vowels, a silent `e` at the end doesn't count. Accented letters
+// are counted as the letters without the accent.
+fn syllables(word: &str) -> usize {
+    let word = deunicode(word).to_lowercase();
+    let is_vowel = |c: char| "aeiouy".contains(c);
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for c in word.chars() {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if count > 1 && word.ends_with('e') && !word.ends_with("le") {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str =
+        "The cat sat on the mat. The cat was happy!\n\nIt purred. Did the dog care?\n";
+
+    #[test]
+    fn paragraphs_are_separated_by_blank_lines() {
+        assert_eq!(
+            paragraphs("One.\r\n\r\nTwo.\r\nStill two.\r\n \r\n\r\nThree."),
+            3
+        );
+        assert_eq!(paragraphs("\n\nOne.\n"), 1);
+    }
+
+    #[test]
+    fn empty_text() {
+        let stats = TextStats::of("", &[], 3);
+        assert_eq!((stats.words, stats.sentences, stats.paragraphs), (0, 0, 0));
+        assert_eq!(sentences(""), 0);
+    }
+
+    #[test]
+    fn counts() {
+        let stats = TextStats::of(TEXT, &[], 3);
+        assert_eq!(stats.words, 16);
+        assert_eq!(stats.sentences, 4);
+        assert_eq!(stats.lines, 3);
+        assert_eq!(stats.paragraphs, 2);
+        assert_eq!(stats.characters, TEXT.chars().count());
+        assert_eq!(
+            stats.top_words,
+            [
+                ("the".to_string(), 4),
+                ("cat".to_string(), 2),
+                ("care".to_string(), 1)
+            ]
+        );
+        let stop_words = ["the".to_string(), "cat".to_string()];
+        assert_eq!(TextStats::of(TEXT, &stop_words, 1).top_words[0].0, "care");
+    }
+
+    #[test]
+    fn syllables_and_readability() {
+        assert_eq!(syllables("cat"), 1);
+        assert_eq!(syllables("happy"), 2);
+        assert_eq!(syllables("care"), 1);
+        assert_eq!(syllables("table"), 2);
+        assert_eq!(syllables("readability"), 5);
+        assert_eq!(syllables("žluťoučký"), 3);
+
+        let stats = TextStats::of("The cat sat on the mat.", &[], 0);
+        assert_eq!(stats.flesch_reading_ease().map(f64::round), Some(116.0));
+        assert_eq!(stats.flesch_kincaid_grade().map(f64::round), Some(-1.0));
+        assert_eq!(TextStats::of("", &[], 0).flesch_kincaid_grade(), None);
+    }
+
+    #[test]
+    fn table_and_json_output() {
+        let options = Options::from_spec("top=1").unwrap();
+        let table = stats("Hello world. Hello!", &options).unwrap();
+        assert!(table.starts_with("statistic            | value\n"));
+        assert!(table.contains("\nwords                |     3\n"));
+        assert!(table.ends_with("word  | count\n------+------\nhello |     2"));
+
+        let options = Options::from_spec("output=json,stop-words=hello").unwrap();
+        let json: Value =
+            serde_json::from_str(&stats("Hello world. Hello!", &options).unwrap()).unwrap();
+        assert_eq!(json["sentences"], 2);
+        assert_eq!(json["average_word_length"], 5.0);
+        assert_eq!(json["top_words"][0]["word"], "world");
+
+        let options = Options::from_spec("output=xml").unwrap();
+        assert!(stats("x", &options).is_err());
+    }
+}
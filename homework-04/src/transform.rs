@@ -0,0 +1,230 @@
+//! Extension point for operations.
+//!
+//! Every operation the tool knows about is a [`Transform`] registered in a
+//! [`Registry`]. The command line, the help text and the interactive mode
+//! all look operations up in the registry, so a transform defined in another
+//! crate works everywhere once it is registered:
+//!
+//! ```
+//! use homework_04::transform::{Registry, Transform};
+//! use homework_04::{Error, Options};
+//!
+//! struct Shout;
+//!
+//! impl Transform for Shout {
+//!     fn name(&self) -> &str {
+//!         "shout"
+//!     }
+//!
+//!     fn description(&self) -> &str {
+//!         "Uppercase the input and add an exclamation mark"
+//!     }
+//!
+//!     fn apply(&self, input: &str, options: &Options) -> Result<String, Error> {
+//!         let marks = options.parse::<usize>("marks")?.unwrap_or(1);
+//!         Ok(format!("{}{}", input.to_uppercase(), "!".repeat(marks)))
+//!     }
+//! }
+//!
+//! let mut registry = Registry::default();
+//! registry.register(Shout).unwrap();
+//!
+//! let pipeline = registry.parse_pipeline("shout:marks=2|reverse").unwrap();
+//! assert_eq!(pipeline.run("hi").unwrap(), "!!IH");
+//! ```
+
+use crate::error::Error;
+use crate::operations::{Pipeline, StringOperation};
+use crate::options::Options;
+use std::io;
+use std::sync::Arc;
+
+/// A named string operation.
+pub trait Transform: Send + Sync {
+    /// Name used on the command line, e.g. `title-case`.
+    fn name(&self) -> &str;
+
+    /// Other names the transform can be called by.
+    fn aliases(&self) -> &[&str] {
+        &[]
+    }
+
+    /// One line description shown in the help text.
+    fn description(&self) -> &str;
+
+    /// What the input is, used when asking the user for it.
+    fn input_hint(&self) -> &str {
+        "the string to modify"
+    }
+
+    /// Transforms the input. Options the transform does not know are ignored.
+    fn apply(&self, input: &str, options: &Options) -> Result<String, Error>;
+
+    /// False when the input should be dropped, e.g. a line which doesn't
+    /// match in batch mode. Called before [`Transform::apply`].
+    fn keep(&self, _input: &str, _options: &Options) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    /// True when the transform works on all the lines of the input at once,
+    /// like `sort-lines`. Batch mode then gives it the whole file instead of
+    /// one line at a time, see [`Pipeline::run_lines`].
+    fn whole_input(&self) -> bool {
+        false
+    }
+
+    /// Writes the result followed by a newline into `out`. Transforms with
+    /// large outputs can override it to write while they work.
+    fn write_to(
+        &self,
+        input: &str,
+        options: &Options,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        writeln!(out, "{}", self.apply(input, options)?)?;
+        Ok(())
+    }
+}
+
+/// Set of transforms which can be looked up by name or alias.
+#[derive(Clone)]
+pub struct Registry {
+    transforms: Vec<Arc<dyn Transform>>,
+}
+
+impl Default for Registry {
+    /// Registry with all the [`StringOperation`]s.
+    fn default() -> Self {
+        let mut registry = Registry::new();
+        for operation in StringOperation::ALL {
+            registry
+                .register(*operation)
+                .expect("builtin operations have unique names");
+        }
+        registry
+    }
+}
+
+impl Registry {
+    /// Empty registry, see [`Registry::default`] for one with the builtins.
+    pub fn new() -> Self {
+        Registry {
+            transforms: Vec::new(),
+        }
+    }
+
+    /// Adds the transform, fails when its name or one of its aliases is taken.
+    pub fn register<T: Transform + 'static>(&mut self, transform: T) -> Result<(), Error> {
+        for name in std::iter::once(transform.name()).chain(transform.aliases().iter().copied()) {
+            if self.get(name).is_some() {
+                return Err(Error::invalid_argument(format!(
+                    "Operation '{}' is already registered",
+                    name
+                )));
+            }
+        }
+        self.transforms.push(Arc::new(transform));
+        Ok(())
+    }
+
+    /// Finds the transform by its name or one of its aliases.
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Transform>> {
+        self.transforms
+            .iter()
+            .find(|t| t.name() == name || t.aliases().contains(&name))
+            .cloned()
+    }
+
+    /// Like [`Registry::get`], with an error listing the available operations.
+    pub fn lookup(&self, name: &str) -> Result<Arc<dyn Transform>, Error> {
+        self.get(name).ok_or_else(|| Error::UnknownOperation {
+            name: name.to_string(),
+            available: self.names().iter().map(|n| n.to_string()).collect(),
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Transform>> {
+        self.transforms.iter()
+    }
+
+    /// Names of the transforms in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.transforms.iter().map(|t| t.name()).collect()
+    }
+
+    /// One line per transform: name, aliases and description.
+    pub fn help(&self) -> String {
+        let labels: Vec<String> = self
+            .transforms
+            .iter()
+            .map(|t| match t.aliases() {
+                [] => t.name().to_string(),
+                aliases => format!("{} ({})", t.name(), aliases.join(", ")),
+            })
+            .collect();
+        let width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+
+        labels
+            .iter()
+            .zip(&self.transforms)
+            .map(|(label, t)| format!("  {:width$}  {}\n", label, t.description(), width = width))
+            .collect()
+    }
+
+    /// Parses `<op>[:<options>]|<op>|...` using the transforms of this registry.
+    pub fn parse_pipeline(&self, s: &str) -> Result<Pipeline, Error> {
+        Pipeline::parse(s, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Noop;
+
+    impl Transform for Noop {
+        fn name(&self) -> &str {
+            "noop"
+        }
+
+        fn aliases(&self) -> &[&str] {
+            &["lower"]
+        }
+
+        fn description(&self) -> &str {
+            "Does nothing"
+        }
+
+        fn apply(&self, input: &str, _options: &Options) -> Result<String, Error> {
+            Ok(input.to_string())
+        }
+    }
+
+    #[test]
+    fn builtins_are_found_by_name_and_alias() {
+        let registry = Registry::default();
+        for operation in StringOperation::ALL {
+            assert_eq!(
+                registry.lookup(operation.name()).unwrap().name(),
+                operation.name()
+            );
+            for alias in operation.aliases() {
+                assert_eq!(registry.get(alias).unwrap().name(), operation.name());
+            }
+        }
+        assert!(registry.lookup("shout").is_err());
+    }
+
+    #[test]
+    fn conflicting_names_are_rejected() {
+        let mut registry = Registry::default();
+        let err = registry.register(Noop).unwrap_err();
+        assert_eq!(err.to_string(), "Operation 'lower' is already registered");
+
+        let mut registry = Registry::new();
+        registry.register(Noop).unwrap();
+        assert_eq!(registry.names(), ["noop"]);
+        assert!(registry.help().contains("noop (lower)  Does nothing"));
+    }
+}
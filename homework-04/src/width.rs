@@ -0,0 +1,105 @@
+//! Width of text as displayed in a terminal.
+//!
+//! East Asian wide characters and most emoji take two columns, combining
+//! marks and zero width joiners none, so `str::len` can't be used for
+//! aligning text.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Number of terminal columns `s` takes.
+pub fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+    /// The extra column of an odd padding goes to the right.
+    Center,
+}
+
+/// Pads `s` with spaces to `width` columns, longer strings are left as they are.
+pub fn pad(s: &str, width: usize, align: Align) -> String {
+    pad_with(s, width, align, ' ')
+}
+
+/// Like [`pad`] with another `fill` character, which should be one column wide.
+pub fn pad_with(s: &str, width: usize, align: Align, fill: char) -> String {
+    let padding = width.saturating_sub(display_width(s));
+    let fill = |n: usize| fill.to_string().repeat(n);
+    match align {
+        Align::Left => format!("{}{}", s, fill(padding)),
+        Align::Right => format!("{}{}", fill(padding), s),
+        Align::Center => format!("{}{}{}", fill(padding / 2), s, fill(padding - padding / 2)),
+    }
+}
+
+/// Cuts `s` to at most `width` columns, ending with `…` when it was cut.
+pub fn truncate(s: &str, width: usize) -> String {
+    truncate_with(s, width, "…")
+}
+
+/// Like [`truncate`] with another `ellipsis`. Grapheme clusters are never
+/// cut in half, so accents and emoji stay whole.
+pub fn truncate_with(s: &str, width: usize, ellipsis: &str) -> String {
+    if display_width(s) <= width {
+        return s.to_string();
+    }
+    // Leave room for the ellipsis, unless it doesn't fit at all
+    let ellipsis = if display_width(ellipsis) <= width {
+        ellipsis
+    } else {
+        ""
+    };
+    let available = width - display_width(ellipsis);
+    let mut truncated = String::new();
+    let mut used = 0;
+    for grapheme in s.graphemes(true) {
+        let grapheme_width = display_width(grapheme);
+        if used + grapheme_width > available {
+            break;
+        }
+        used += grapheme_width;
+        truncated.push_str(grapheme);
+    }
+    truncated.push_str(ellipsis);
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_and_zero_width_characters() {
+        assert_eq!(display_width("Žluťoučký"), 9);
+        assert_eq!(display_width("東京"), 4);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("👨\u{200d}👩\u{200d}👧"), 2);
+    }
+
+    #[test]
+    fn pads_by_display_width() {
+        assert_eq!(pad("東京", 6, Align::Left), "東京  ");
+        assert_eq!(pad("42", 4, Align::Right), "  42");
+        assert_eq!(pad("long", 2, Align::Left), "long");
+        assert_eq!(pad("ab", 5, Align::Center), " ab  ");
+        assert_eq!(pad_with("東京", 8, Align::Center, '*'), "**東京**");
+    }
+
+    #[test]
+    fn truncates_with_ellipsis() {
+        assert_eq!(truncate("Hello World", 6), "Hello…");
+        assert_eq!(truncate("東京都", 4), "東…");
+        assert_eq!(truncate("short", 5), "short");
+        assert_eq!(truncate("cafe\u{301}s!", 5), "cafe\u{301}…");
+        assert_eq!(
+            truncate("👨\u{200d}👩\u{200d}👧 family", 3),
+            "👨\u{200d}👩\u{200d}👧…"
+        );
+        assert_eq!(truncate_with("Hello World", 8, "..."), "Hello...");
+        assert_eq!(truncate_with("Hello World", 2, "..."), "He");
+    }
+}
@@ -0,0 +1,29 @@
+// Tables rendered from the fixtures must match the `.txt` next to them
+use homework_04::csv_operations::parse_as_csv;
+use homework_04::Options;
+use std::fs;
+use std::path::Path;
+
+fn assert_golden(name: &str) {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let csv_path = fixtures.join(format!("{}.csv", name));
+    let expected = fs::read_to_string(fixtures.join(format!("{}.txt", name))).unwrap();
+
+    let table = parse_as_csv(csv_path.to_str().unwrap(), &Options::new()).unwrap();
+    assert_eq!(table, expected, "table for {}.csv differs", name);
+}
+
+#[test]
+fn czech_diacritics() {
+    assert_golden("czech");
+}
+
+#[test]
+fn japanese_wide_characters() {
+    assert_golden("japanese");
+}
+
+#[test]
+fn emoji_and_combining_marks() {
+    assert_golden("mixed");
+}